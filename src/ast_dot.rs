@@ -0,0 +1,197 @@
+use std::fmt::Write;
+use std::rc::Rc;
+
+use crate::expr::{Assign, Binary, Call, ExprArena, ExprId, ExprVisitor, Literal, Logical, Unary};
+use crate::program::Program;
+use crate::stmt::{Function, If, Return, Stmt, StmtVisitor, Var, While};
+use crate::token::Token;
+
+/// Renders a program as a Graphviz digraph, one node per statement and
+/// expression.
+pub fn to_dot(program: &Program) -> String {
+    let mut writer = DotWriter {
+        arena: program.arena(),
+        dot: String::from("digraph ast {\n    node [shape=box];\n"),
+        next_node: 0,
+    };
+    let root = writer.node("program");
+    for statement in program.statements() {
+        let child = writer.stmt(statement);
+        writer.edge(&root, &child);
+    }
+    writer.dot.push_str("}\n");
+    writer.dot
+}
+
+struct DotWriter<'a> {
+    arena: &'a ExprArena,
+    dot: String,
+    next_node: usize,
+}
+
+impl DotWriter<'_> {
+    // Writes the statement and its subtree, returning the node's name.
+    fn stmt(&mut self, stmt: &Stmt) -> String {
+        stmt.accept(self)
+    }
+
+    // Writes the expression and its subtree, returning the node's name.
+    fn expr(&mut self, id: ExprId) -> String {
+        id.accept(self.arena, self)
+    }
+
+    // Writes the statements under the node `name`, returning it.
+    fn stmts_under(&mut self, name: String, statements: &[Stmt]) -> String {
+        for statement in statements {
+            let child = self.stmt(statement);
+            self.edge(&name, &child);
+        }
+        name
+    }
+
+    // Writes the expressions under the node `name`, returning it.
+    fn exprs_under(&mut self, name: String, exprs: impl IntoIterator<Item = ExprId>) -> String {
+        for expr in exprs {
+            let child = self.expr(expr);
+            self.edge(&name, &child);
+        }
+        name
+    }
+
+    fn node(&mut self, label: &str) -> String {
+        let name = format!("n{}", self.next_node);
+        self.next_node += 1;
+        writeln!(self.dot, "    {name} [label=\"{}\"];", escape(label)).unwrap();
+        name
+    }
+
+    fn edge(&mut self, from: &str, to: &str) {
+        writeln!(self.dot, "    {from} -> {to};").unwrap();
+    }
+}
+
+impl StmtVisitor<String> for DotWriter<'_> {
+    fn visit_block(&mut self, statements: &[Stmt]) -> String {
+        let name = self.node("block");
+        self.stmts_under(name, statements)
+    }
+
+    fn visit_break(&mut self, _: &Token) -> String {
+        self.node("break")
+    }
+
+    fn visit_continue(&mut self, _: &Token) -> String {
+        self.node("continue")
+    }
+
+    fn visit_expression(&mut self, expr: ExprId) -> String {
+        let name = self.node("expr");
+        self.exprs_under(name, [expr])
+    }
+
+    fn visit_function(&mut self, function: &Rc<Function>) -> String {
+        let params: Vec<&str> = function
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_str())
+            .collect();
+        let name = self.node(&format!(
+            "fun {}({})",
+            function.name.lexeme,
+            params.join(", ")
+        ));
+        self.stmts_under(name, &function.body)
+    }
+
+    fn visit_if(&mut self, if_stmt: &If) -> String {
+        let name = self.node("if");
+        let name = self.exprs_under(name, [if_stmt.condition]);
+        let then_branch = self.stmt(&if_stmt.then_branch);
+        self.edge(&name, &then_branch);
+        if let Some(else_branch) = &if_stmt.else_branch {
+            let else_branch = self.stmt(else_branch);
+            self.edge(&name, &else_branch);
+        }
+        name
+    }
+
+    fn visit_print(&mut self, expr: ExprId) -> String {
+        let name = self.node("print");
+        self.exprs_under(name, [expr])
+    }
+
+    fn visit_return(&mut self, return_stmt: &Return) -> String {
+        let name = self.node("return");
+        self.exprs_under(name, return_stmt.value)
+    }
+
+    fn visit_var(&mut self, var: &Var) -> String {
+        let name = self.node(&format!("var {}", var.name.lexeme));
+        self.exprs_under(name, var.initializer)
+    }
+
+    fn visit_while(&mut self, while_stmt: &While) -> String {
+        let name = self.node("while");
+        let name = self.exprs_under(name, [while_stmt.condition]);
+        let body = self.stmt(&while_stmt.body);
+        self.edge(&name, &body);
+        self.exprs_under(name, while_stmt.increment)
+    }
+}
+
+impl ExprVisitor<String> for DotWriter<'_> {
+    fn visit_assign(&mut self, _: ExprId, assign: &Assign) -> String {
+        let name = self.node(&format!("{} =", assign.name.lexeme));
+        self.exprs_under(name, [assign.value])
+    }
+
+    fn visit_binary(&mut self, _: ExprId, binary: &Binary) -> String {
+        let name = self.node(&binary.operator.lexeme);
+        self.exprs_under(name, [binary.left, binary.right])
+    }
+
+    fn visit_call(&mut self, _: ExprId, call: &Call) -> String {
+        let name = self.node("call");
+        let name = self.exprs_under(name, [call.callee]);
+        self.exprs_under(name, call.arguments.iter().copied())
+    }
+
+    fn visit_grouping(&mut self, _: ExprId, expr: ExprId) -> String {
+        let name = self.node("group");
+        self.exprs_under(name, [expr])
+    }
+
+    fn visit_literal(&mut self, _: ExprId, literal: &Literal) -> String {
+        self.node(&literal_label(literal))
+    }
+
+    fn visit_logical(&mut self, _: ExprId, logical: &Logical) -> String {
+        let name = self.node(&logical.operator.lexeme);
+        self.exprs_under(name, [logical.left, logical.right])
+    }
+
+    fn visit_unary(&mut self, _: ExprId, unary: &Unary) -> String {
+        let name = self.node(&unary.operator.lexeme);
+        self.exprs_under(name, [unary.right])
+    }
+
+    fn visit_variable(&mut self, _: ExprId, name: &Token) -> String {
+        self.node(&name.lexeme)
+    }
+}
+
+pub(crate) fn literal_label(literal: &Literal) -> String {
+    match literal {
+        Literal::Nil => String::from("nil"),
+        Literal::Number(number) => number.to_string(),
+        Literal::Bool(value) => value.to_string(),
+        Literal::String(string) => format!("\"{string}\""),
+    }
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
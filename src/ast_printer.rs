@@ -0,0 +1,243 @@
+use std::rc::Rc;
+
+use crate::ast_dot::literal_label;
+use crate::expr::{Assign, Binary, Call, ExprArena, ExprId, ExprVisitor, Literal, Logical, Unary};
+use crate::program::Program;
+use crate::stmt::{Function, If, Return, Stmt, StmtVisitor, Var, While};
+use crate::token::Token;
+
+/// How [`print`] writes expressions. Statements are always parenthesized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Notation {
+    /// Prefix S-expressions, like `(* (- 123) (group 45.67))`.
+    Lisp,
+    /// Reverse Polish Notation, like `123 ~ 45.67 *`. Negation is written
+    /// `~` to tell it apart from subtraction, and a call ends in
+    /// `call/N` for its N arguments.
+    Rpn,
+}
+
+/// Renders a program one top-level statement per line.
+pub fn print(program: &Program, notation: Notation) -> String {
+    let mut printer = AstPrinter {
+        arena: program.arena(),
+        notation,
+    };
+    program
+        .statements()
+        .iter()
+        .map(|statement| printer.stmt(statement) + "\n")
+        .collect()
+}
+
+struct AstPrinter<'a> {
+    arena: &'a ExprArena,
+    notation: Notation,
+}
+
+impl AstPrinter<'_> {
+    fn stmt(&mut self, stmt: &Stmt) -> String {
+        stmt.accept(self)
+    }
+
+    fn expr(&self, id: ExprId) -> String {
+        match self.notation {
+            Notation::Lisp => Lisp { arena: self.arena }.expr(id),
+            Notation::Rpn => Rpn { arena: self.arena }.expr(id),
+        }
+    }
+}
+
+impl StmtVisitor<String> for AstPrinter<'_> {
+    fn visit_block(&mut self, statements: &[Stmt]) -> String {
+        parenthesize("block", statements.iter().map(|s| self.stmt(s)))
+    }
+
+    fn visit_break(&mut self, _: &Token) -> String {
+        parenthesize("break", [])
+    }
+
+    fn visit_continue(&mut self, _: &Token) -> String {
+        parenthesize("continue", [])
+    }
+
+    fn visit_expression(&mut self, expr: ExprId) -> String {
+        parenthesize(";", [self.expr(expr)])
+    }
+
+    fn visit_function(&mut self, function: &Rc<Function>) -> String {
+        let params: Vec<&str> = function
+            .params
+            .iter()
+            .map(|param| param.lexeme.as_str())
+            .collect();
+        let head = format!("fun {}({})", function.name.lexeme, params.join(" "));
+        parenthesize(&head, function.body.iter().map(|s| self.stmt(s)))
+    }
+
+    fn visit_if(&mut self, if_stmt: &If) -> String {
+        let mut parts = vec![
+            self.expr(if_stmt.condition),
+            self.stmt(&if_stmt.then_branch),
+        ];
+        parts.extend(if_stmt.else_branch.as_deref().map(|s| self.stmt(s)));
+        parenthesize("if", parts)
+    }
+
+    fn visit_print(&mut self, expr: ExprId) -> String {
+        parenthesize("print", [self.expr(expr)])
+    }
+
+    fn visit_return(&mut self, return_stmt: &Return) -> String {
+        parenthesize("return", return_stmt.value.map(|value| self.expr(value)))
+    }
+
+    fn visit_var(&mut self, var: &Var) -> String {
+        match var.initializer {
+            Some(initializer) => parenthesize(
+                &format!("var {} =", var.name.lexeme),
+                [self.expr(initializer)],
+            ),
+            None => parenthesize(&format!("var {}", var.name.lexeme), []),
+        }
+    }
+
+    fn visit_while(&mut self, while_stmt: &While) -> String {
+        let mut parts = vec![self.expr(while_stmt.condition), self.stmt(&while_stmt.body)];
+        parts.extend(while_stmt.increment.map(|increment| self.expr(increment)));
+        parenthesize("while", parts)
+    }
+}
+
+// Writes expressions in Lisp notation.
+struct Lisp<'a> {
+    arena: &'a ExprArena,
+}
+
+impl Lisp<'_> {
+    fn expr(&mut self, id: ExprId) -> String {
+        id.accept(self.arena, self)
+    }
+}
+
+impl ExprVisitor<String> for Lisp<'_> {
+    fn visit_assign(&mut self, _: ExprId, assign: &Assign) -> String {
+        parenthesize(
+            &format!("= {}", assign.name.lexeme),
+            [self.expr(assign.value)],
+        )
+    }
+
+    fn visit_binary(&mut self, _: ExprId, binary: &Binary) -> String {
+        parenthesize(
+            &binary.operator.lexeme,
+            [self.expr(binary.left), self.expr(binary.right)],
+        )
+    }
+
+    fn visit_call(&mut self, _: ExprId, call: &Call) -> String {
+        parenthesize(
+            "call",
+            [call.callee]
+                .iter()
+                .chain(&call.arguments)
+                .map(|&id| self.expr(id)),
+        )
+    }
+
+    fn visit_grouping(&mut self, _: ExprId, expr: ExprId) -> String {
+        parenthesize("group", [self.expr(expr)])
+    }
+
+    fn visit_literal(&mut self, _: ExprId, literal: &Literal) -> String {
+        literal_label(literal)
+    }
+
+    fn visit_logical(&mut self, _: ExprId, logical: &Logical) -> String {
+        parenthesize(
+            &logical.operator.lexeme,
+            [self.expr(logical.left), self.expr(logical.right)],
+        )
+    }
+
+    fn visit_unary(&mut self, _: ExprId, unary: &Unary) -> String {
+        parenthesize(&unary.operator.lexeme, [self.expr(unary.right)])
+    }
+
+    fn visit_variable(&mut self, _: ExprId, name: &Token) -> String {
+        name.lexeme.clone()
+    }
+}
+
+// Writes expressions in Reverse Polish Notation.
+struct Rpn<'a> {
+    arena: &'a ExprArena,
+}
+
+impl Rpn<'_> {
+    fn expr(&mut self, id: ExprId) -> String {
+        id.accept(self.arena, self)
+    }
+}
+
+impl ExprVisitor<String> for Rpn<'_> {
+    fn visit_assign(&mut self, _: ExprId, assign: &Assign) -> String {
+        format!("{} {} =", assign.name.lexeme, self.expr(assign.value))
+    }
+
+    fn visit_binary(&mut self, _: ExprId, binary: &Binary) -> String {
+        format!(
+            "{} {} {}",
+            self.expr(binary.left),
+            self.expr(binary.right),
+            binary.operator.lexeme
+        )
+    }
+
+    fn visit_call(&mut self, _: ExprId, call: &Call) -> String {
+        let mut parts = vec![self.expr(call.callee)];
+        parts.extend(call.arguments.iter().map(|&argument| self.expr(argument)));
+        parts.push(format!("call/{}", call.arguments.len()));
+        parts.join(" ")
+    }
+
+    // Postfix needs no grouping, the order is in the notation.
+    fn visit_grouping(&mut self, _: ExprId, expr: ExprId) -> String {
+        self.expr(expr)
+    }
+
+    fn visit_literal(&mut self, _: ExprId, literal: &Literal) -> String {
+        literal_label(literal)
+    }
+
+    fn visit_logical(&mut self, _: ExprId, logical: &Logical) -> String {
+        format!(
+            "{} {} {}",
+            self.expr(logical.left),
+            self.expr(logical.right),
+            logical.operator.lexeme
+        )
+    }
+
+    fn visit_unary(&mut self, _: ExprId, unary: &Unary) -> String {
+        let operator = match unary.operator.lexeme.as_str() {
+            "-" => "~",
+            operator => operator,
+        };
+        format!("{} {operator}", self.expr(unary.right))
+    }
+
+    fn visit_variable(&mut self, _: ExprId, name: &Token) -> String {
+        name.lexeme.clone()
+    }
+}
+
+fn parenthesize(name: &str, parts: impl IntoIterator<Item = String>) -> String {
+    let mut text = format!("({name}");
+    for part in parts {
+        text.push(' ');
+        text.push_str(&part);
+    }
+    text.push(')');
+    text
+}
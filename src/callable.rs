@@ -0,0 +1,154 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::environment::Environment;
+use crate::interpreter::{Interpreter, RuntimeError, Unwind};
+use crate::program::Program;
+use crate::stmt::Function;
+use crate::token::Token;
+use crate::value::Value;
+
+/// Something that can be called from Lox code.
+pub trait LoxCallable: fmt::Debug + fmt::Display {
+    /// Number of arguments a call must pass.
+    fn arity(&self) -> usize;
+
+    /// Calls with `arguments`, which the caller has already checked
+    /// against [`arity`](LoxCallable::arity). `paren` is the call's
+    /// closing parenthesis, where an error raised by the call is reported.
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError>;
+
+    /// The scope the callable closes over, if it has one.
+    fn closure(&self) -> Option<&Rc<RefCell<Environment>>> {
+        None
+    }
+}
+
+/// A function declared in Lox code, closing over the scope it was
+/// declared in.
+pub struct LoxFunction {
+    declaration: Rc<Function>,
+    // The program the declaration's expressions live in.
+    program: Rc<Program>,
+    // A function stored in the scope it closes over keeps that scope alive
+    // through a reference cycle. The interpreter empties such scopes once
+    // nothing can reach them, which frees both.
+    closure: Rc<RefCell<Environment>>,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: Rc<Function>,
+        program: Rc<Program>,
+        closure: Rc<RefCell<Environment>>,
+    ) -> Self {
+        LoxFunction {
+            declaration,
+            program,
+            closure,
+        }
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        _: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let mut environment = Environment::new(Rc::clone(&self.closure));
+        for (param, argument) in self.declaration.params.iter().zip(arguments) {
+            environment.define(&param.lexeme, argument);
+        }
+        match interpreter.execute_body(&self.program, &self.declaration.body, environment) {
+            Ok(()) => Ok(Value::Nil),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(error)) => Err(error),
+            // The parser rejects a break or continue outside any loop, but
+            // without it one ends the call.
+            Err(Unwind::Break | Unwind::Continue) => Ok(Value::Nil),
+        }
+    }
+
+    fn closure(&self) -> Option<&Rc<RefCell<Environment>>> {
+        Some(&self.closure)
+    }
+}
+
+// The closure is left out, it may well contain this function.
+impl fmt::Debug for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoxFunction")
+            .field("name", &self.declaration.name.lexeme)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name.lexeme)
+    }
+}
+
+/// A function implemented in Rust, such as the `clock` builtin. It fails
+/// with a message, which becomes a runtime error at the call.
+pub struct NativeFunction {
+    name: String,
+    arity: usize,
+    function: Box<dyn Fn(Vec<Value>) -> Result<Value, String>>,
+}
+
+impl NativeFunction {
+    pub fn new(
+        name: &str,
+        arity: usize,
+        function: impl Fn(Vec<Value>) -> Result<Value, String> + 'static,
+    ) -> Self {
+        NativeFunction {
+            name: name.to_string(),
+            arity,
+            function: Box::new(function),
+        }
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(
+        &self,
+        _: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        (self.function)(arguments).map_err(|message| RuntimeError::new(paren, &message))
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
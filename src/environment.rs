@@ -0,0 +1,112 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::interpreter::RuntimeError;
+use crate::token::Token;
+use crate::value::Value;
+
+/// Variable bindings for one scope, chained to the scope around it.
+///
+/// Scopes are shared: a function keeps the scope it was declared in alive
+/// for as long as the function itself. A function stored in that scope, or
+/// one around it, makes a reference cycle that the interpreter breaks once
+/// the scope is out of reach.
+#[derive(Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Environment {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    // Redefining an existing name is allowed and replaces its value.
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = self.values.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(undefined(name)),
+        }
+    }
+
+    /// Reads `name` from the scope `distance` steps out from this one,
+    /// where the resolver found it.
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Value, RuntimeError> {
+        if distance > 0 {
+            return self.enclosing().borrow().get_at(distance - 1, name);
+        }
+        self.values
+            .get(&name.lexeme)
+            .cloned()
+            .ok_or_else(|| undefined(name))
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(undefined(name)),
+        }
+    }
+
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &Token,
+        value: Value,
+    ) -> Result<(), RuntimeError> {
+        if distance > 0 {
+            return self
+                .enclosing()
+                .borrow_mut()
+                .assign_at(distance - 1, name, value);
+        }
+        match self.values.get_mut(&name.lexeme) {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => Err(undefined(name)),
+        }
+    }
+
+    /// The values bound in this scope itself.
+    pub(crate) fn values(&self) -> impl Iterator<Item = &Value> {
+        self.values.values()
+    }
+
+    pub(crate) fn enclosing_scope(&self) -> Option<&Rc<RefCell<Environment>>> {
+        self.enclosing.as_ref()
+    }
+
+    /// Drops every binding in this scope, and with them any function that
+    /// kept it alive.
+    pub(crate) fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    fn enclosing(&self) -> &Rc<RefCell<Environment>> {
+        self.enclosing
+            .as_ref()
+            .expect("resolved distance is within the scope chain")
+    }
+}
+
+fn undefined(name: &Token) -> RuntimeError {
+    RuntimeError::new(name, &format!("Undefined variable '{}'.", name.lexeme))
+}
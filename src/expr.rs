@@ -1,20 +1,105 @@
-use crate::token::Token;
+use std::ops::Index;
+
+use crate::token::{Span, Token};
+
+/// Handle to an [`Expr`] stored in an [`ExprArena`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExprId(usize);
+
+/// Owns every expression node of a parse; children refer to each other by
+/// [`ExprId`] so the whole tree is freed at once.
+#[derive(Debug, Default)]
+pub struct ExprArena {
+    exprs: Vec<Expr>,
+    spans: Vec<Span>,
+}
+
+impl ExprArena {
+    pub fn alloc(&mut self, expr: Expr, span: Span) -> ExprId {
+        self.exprs.push(expr);
+        self.spans.push(span);
+        ExprId(self.exprs.len() - 1)
+    }
+
+    /// Source range covered by the expression, including its children.
+    pub fn span(&self, id: ExprId) -> Span {
+        self.spans[id.0]
+    }
+}
+
+impl ExprId {
+    /// Calls the `visitor` method for this expression's variant.
+    pub fn accept<R, V>(self, arena: &ExprArena, visitor: &mut V) -> R
+    where
+        V: ExprVisitor<R> + ?Sized,
+    {
+        match &arena[self] {
+            Expr::Assign(assign) => visitor.visit_assign(self, assign),
+            Expr::Binary(binary) => visitor.visit_binary(self, binary),
+            Expr::Call(call) => visitor.visit_call(self, call),
+            Expr::Grouping(expr) => visitor.visit_grouping(self, *expr),
+            Expr::Literal(literal) => visitor.visit_literal(self, literal),
+            Expr::Logical(logical) => visitor.visit_logical(self, logical),
+            Expr::Unary(unary) => visitor.visit_unary(self, unary),
+            Expr::Variable(name) => visitor.visit_variable(self, name),
+        }
+    }
+}
+
+/// A pass over expressions, with one method per [`Expr`] variant. Each
+/// method also gets the [`ExprId`] of the expression it visits.
+pub trait ExprVisitor<R> {
+    fn visit_assign(&mut self, id: ExprId, assign: &Assign) -> R;
+    fn visit_binary(&mut self, id: ExprId, binary: &Binary) -> R;
+    fn visit_call(&mut self, id: ExprId, call: &Call) -> R;
+    fn visit_grouping(&mut self, id: ExprId, expr: ExprId) -> R;
+    fn visit_literal(&mut self, id: ExprId, literal: &Literal) -> R;
+    fn visit_logical(&mut self, id: ExprId, logical: &Logical) -> R;
+    fn visit_unary(&mut self, id: ExprId, unary: &Unary) -> R;
+    fn visit_variable(&mut self, id: ExprId, name: &Token) -> R;
+}
+
+impl Index<ExprId> for ExprArena {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.0]
+    }
+}
 
 #[derive(Debug)]
 pub enum Expr {
+    Assign(Assign),
     Binary(Binary),
-    Grouping(Box<Self>),
+    Call(Call),
+    Grouping(ExprId),
     Literal(Literal),
+    Logical(Logical),
     Unary(Unary),
+    Variable(Token),
+}
+
+#[derive(Debug)]
+pub struct Assign {
+    pub name: Token,
+    pub value: ExprId,
 }
 
 #[derive(Debug)]
 pub struct Binary {
-    pub left: Box<Expr>,
-    pub right: Box<Expr>,
+    pub left: ExprId,
+    pub right: ExprId,
     pub operator: Token,
 }
 
+#[derive(Debug)]
+pub struct Call {
+    pub callee: ExprId,
+    // The closing parenthesis, which runtime errors in the call point at.
+    pub paren: Token,
+    pub arguments: Vec<ExprId>,
+}
+
 #[derive(Debug, Clone, Default)]
 pub enum Literal {
     #[default]
@@ -24,8 +109,15 @@ pub enum Literal {
     String(String),
 }
 
+#[derive(Debug)]
+pub struct Logical {
+    pub left: ExprId,
+    pub right: ExprId,
+    pub operator: Token,
+}
+
 #[derive(Debug)]
 pub struct Unary {
     pub operator: Token,
-    pub right: Box<Expr>,
+    pub right: ExprId,
 }
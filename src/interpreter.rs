@@ -0,0 +1,502 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::mem;
+use std::rc::{Rc, Weak};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::callable::{LoxFunction, NativeFunction};
+use crate::environment::Environment;
+use crate::expr::{Assign, Binary, Call, ExprId, ExprVisitor, Literal, Logical, Unary};
+use crate::program::Program;
+use crate::stmt::{Function, If, Return, Stmt, StmtVisitor, Var, While};
+use crate::token::{Token, TokenType};
+use crate::value::Value;
+
+/// An error raised while evaluating, blamed on the operator token.
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub token: Token,
+    pub message: String,
+}
+
+impl RuntimeError {
+    pub(crate) fn new(token: &Token, message: &str) -> Self {
+        RuntimeError {
+            token: token.clone(),
+            message: message.to_string(),
+        }
+    }
+}
+
+// How deeply calls may nest, and statements and expressions across all of
+// them, before the program is stopped, so that runaway recursion can't
+// overflow the host stack. The parser bounds how deeply a function's body
+// nests, so it's enough to check the second limit at calls too.
+const MAX_CALL_DEPTH: usize = 1000;
+const MAX_NESTING: usize = 20_000;
+
+/// Why a statement stopped before running to its end.
+#[derive(Debug)]
+pub(crate) enum Unwind {
+    Error(RuntimeError),
+    /// A `return`, carrying its value back to the call.
+    Return(Value),
+    /// A `break`, ending the innermost loop.
+    Break,
+    /// A `continue`, ending the innermost loop's current iteration.
+    Continue,
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(error: RuntimeError) -> Self {
+        Unwind::Error(error)
+    }
+}
+
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    // The program whose code is running, swapped for the one a function
+    // was declared in while it's called.
+    program: Rc<Program>,
+    // Every scope a function has closed over, which is where a reference
+    // cycle could keep a scope alive after it's out of reach.
+    closures: Vec<Weak<RefCell<Environment>>>,
+    call_depth: usize,
+    nesting: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let globals = Rc::new(RefCell::new(Environment::default()));
+        let mut interpreter = Interpreter {
+            environment: Rc::clone(&globals),
+            globals,
+            program: Rc::default(),
+            closures: Vec::new(),
+            call_depth: 0,
+            nesting: 0,
+        };
+        // Seconds since the Unix epoch, for timing code.
+        interpreter.define_native("clock", 0, |_| {
+            let since_epoch = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            Ok(Value::Number(since_epoch.as_secs_f64()))
+        });
+        interpreter
+    }
+
+    /// Makes `function` callable from Lox as the global `name`, taking
+    /// `arity` arguments. An `Err` it returns stops the program with that
+    /// message as a runtime error. Defining a name again replaces it.
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(Vec<Value>) -> Result<Value, String> + 'static,
+    ) {
+        let native = NativeFunction::new(name, arity, function);
+        self.globals
+            .borrow_mut()
+            .define(name, Value::Callable(Rc::new(native)));
+    }
+
+    /// Runs `program`, then frees the scopes of functions it can no longer
+    /// reach. A function an embedder has kept hold of outside the globals
+    /// counts as out of reach, and calling it later may fail.
+    pub fn interpret(&mut self, program: &Rc<Program>) -> Result<(), RuntimeError> {
+        let previous = mem::replace(&mut self.program, Rc::clone(program));
+        let mut result = Ok(());
+        for statement in program.statements() {
+            match self.execute(statement) {
+                Ok(()) => {}
+                Err(Unwind::Error(error)) => {
+                    result = Err(error);
+                    break;
+                }
+                // The resolver rejects a return outside any function, and the
+                // parser a break or continue outside any loop, but without
+                // them one ends the program.
+                Err(Unwind::Return(_) | Unwind::Break | Unwind::Continue) => break,
+            }
+        }
+        self.program = previous;
+        self.collect_cycles();
+        result
+    }
+
+    // Empties the scopes that functions closed over, and the scopes around
+    // them, once nothing reachable from the globals refers to them. Those
+    // can only still be alive through a reference cycle, which emptying
+    // them breaks.
+    fn collect_cycles(&mut self) {
+        let mut reachable = HashSet::new();
+        let mut pending = vec![Rc::clone(&self.globals), Rc::clone(&self.environment)];
+        while let Some(environment) = pending.pop() {
+            if !reachable.insert(Rc::as_ptr(&environment)) {
+                continue;
+            }
+            let environment = environment.borrow();
+            pending.extend(environment.enclosing_scope().cloned());
+            pending.extend(environment.values().filter_map(|value| match value {
+                Value::Callable(callable) => callable.closure().cloned(),
+                _ => None,
+            }));
+        }
+        let mut kept = HashSet::new();
+        let closures = mem::take(&mut self.closures);
+        for closure in closures {
+            let mut scope = closure.upgrade();
+            if let Some(environment) = &scope {
+                if reachable.contains(&Rc::as_ptr(environment)) {
+                    if kept.insert(Rc::as_ptr(environment)) {
+                        self.closures.push(closure);
+                    }
+                    continue;
+                }
+            }
+            while let Some(environment) = scope {
+                if reachable.contains(&Rc::as_ptr(&environment)) {
+                    break;
+                }
+                scope = environment.borrow().enclosing_scope().cloned();
+                environment.borrow_mut().clear();
+            }
+        }
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        self.nesting += 1;
+        let result = stmt.accept(self);
+        self.nesting -= 1;
+        result
+    }
+
+    /// Runs the statements in `environment`, then returns to the current
+    /// one.
+    fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Environment,
+    ) -> Result<(), Unwind> {
+        let previous = mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.execute(statement));
+        self.environment = previous;
+        result
+    }
+
+    /// Runs the body of a function declared in `program`, like
+    /// [`execute_block`](Interpreter::execute_block), then returns to the
+    /// current program.
+    pub(crate) fn execute_body(
+        &mut self,
+        program: &Rc<Program>,
+        body: &[Stmt],
+        environment: Environment,
+    ) -> Result<(), Unwind> {
+        let previous = mem::replace(&mut self.program, Rc::clone(program));
+        let result = self.execute_block(body, environment);
+        self.program = previous;
+        result
+    }
+
+    fn evaluate(&mut self, expr: ExprId) -> Result<Value, RuntimeError> {
+        self.nesting += 1;
+        let program = Rc::clone(&self.program);
+        let value = expr.accept(program.arena(), self);
+        self.nesting -= 1;
+        value
+    }
+
+    fn look_up_variable(&self, name: &Token, expr: ExprId) -> Result<Value, RuntimeError> {
+        match self.program.depth(expr) {
+            Some(distance) => self.environment.borrow().get_at(distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+}
+
+impl StmtVisitor<Result<(), Unwind>> for Interpreter {
+    fn visit_block(&mut self, statements: &[Stmt]) -> Result<(), Unwind> {
+        let environment = Environment::new(Rc::clone(&self.environment));
+        self.execute_block(statements, environment)
+    }
+
+    fn visit_break(&mut self, _: &Token) -> Result<(), Unwind> {
+        Err(Unwind::Break)
+    }
+
+    fn visit_continue(&mut self, _: &Token) -> Result<(), Unwind> {
+        Err(Unwind::Continue)
+    }
+
+    fn visit_expression(&mut self, expr: ExprId) -> Result<(), Unwind> {
+        self.evaluate(expr)?;
+        Ok(())
+    }
+
+    fn visit_function(&mut self, function: &Rc<Function>) -> Result<(), Unwind> {
+        let closure = LoxFunction::new(
+            Rc::clone(function),
+            Rc::clone(&self.program),
+            Rc::clone(&self.environment),
+        );
+        let value = Value::Callable(Rc::new(closure));
+        self.closures.push(Rc::downgrade(&self.environment));
+        self.environment
+            .borrow_mut()
+            .define(&function.name.lexeme, value);
+        Ok(())
+    }
+
+    fn visit_if(&mut self, if_stmt: &If) -> Result<(), Unwind> {
+        if self.evaluate(if_stmt.condition)?.is_truthy() {
+            self.execute(&if_stmt.then_branch)?;
+        } else if let Some(else_branch) = &if_stmt.else_branch {
+            self.execute(else_branch)?;
+        }
+        Ok(())
+    }
+
+    fn visit_print(&mut self, expr: ExprId) -> Result<(), Unwind> {
+        println!("{}", self.evaluate(expr)?);
+        Ok(())
+    }
+
+    fn visit_return(&mut self, return_stmt: &Return) -> Result<(), Unwind> {
+        let value = match return_stmt.value {
+            Some(value) => self.evaluate(value)?,
+            None => Value::Nil,
+        };
+        Err(Unwind::Return(value))
+    }
+
+    fn visit_var(&mut self, var: &Var) -> Result<(), Unwind> {
+        let value = match var.initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Value::Nil,
+        };
+        self.environment
+            .borrow_mut()
+            .define(&var.name.lexeme, value);
+        Ok(())
+    }
+
+    fn visit_while(&mut self, while_stmt: &While) -> Result<(), Unwind> {
+        while self.evaluate(while_stmt.condition)?.is_truthy() {
+            match self.execute(&while_stmt.body) {
+                Ok(()) | Err(Unwind::Continue) => {}
+                Err(Unwind::Break) => break,
+                Err(unwind) => return Err(unwind),
+            }
+            if let Some(increment) = while_stmt.increment {
+                self.evaluate(increment)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ExprVisitor<Result<Value, RuntimeError>> for Interpreter {
+    fn visit_assign(&mut self, id: ExprId, assign: &Assign) -> Result<Value, RuntimeError> {
+        let value = self.evaluate(assign.value)?;
+        match self.program.depth(id) {
+            Some(distance) => {
+                self.environment
+                    .borrow_mut()
+                    .assign_at(distance, &assign.name, value.clone())?
+            }
+            None => self
+                .globals
+                .borrow_mut()
+                .assign(&assign.name, value.clone())?,
+        }
+        Ok(value)
+    }
+
+    fn visit_binary(&mut self, _: ExprId, binary: &Binary) -> Result<Value, RuntimeError> {
+        let left = self.evaluate(binary.left)?;
+        let right = self.evaluate(binary.right)?;
+        let operator = &binary.operator;
+
+        match operator.token_type {
+            TokenType::Plus => match (left, right) {
+                (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left + right)),
+                (Value::String(left), Value::String(right)) => Ok(Value::String(left + &right)),
+                _ => Err(RuntimeError::new(
+                    operator,
+                    "Operands must be two numbers or two strings.",
+                )),
+            },
+            TokenType::BangEqual => Ok(Value::Bool(left != right)),
+            TokenType::EqualEqual => Ok(Value::Bool(left == right)),
+            _ => {
+                let (left, right) = number_operands(operator, &left, &right)?;
+                Ok(match operator.token_type {
+                    TokenType::Minus => Value::Number(left - right),
+                    TokenType::Slash => Value::Number(left / right),
+                    TokenType::Star => Value::Number(left * right),
+                    TokenType::Greater => Value::Bool(left > right),
+                    TokenType::GreaterEqual => Value::Bool(left >= right),
+                    TokenType::Less => Value::Bool(left < right),
+                    TokenType::LessEqual => Value::Bool(left <= right),
+                    _ => unreachable!("parser only builds arithmetic, comparison and equality"),
+                })
+            }
+        }
+    }
+
+    fn visit_call(&mut self, _: ExprId, call: &Call) -> Result<Value, RuntimeError> {
+        let callee = self.evaluate(call.callee)?;
+        let mut arguments = Vec::with_capacity(call.arguments.len());
+        for &argument in &call.arguments {
+            arguments.push(self.evaluate(argument)?);
+        }
+
+        let Value::Callable(function) = callee else {
+            return Err(RuntimeError::new(
+                &call.paren,
+                "Can only call functions and classes.",
+            ));
+        };
+        if arguments.len() != function.arity() {
+            return Err(RuntimeError::new(
+                &call.paren,
+                &format!(
+                    "Expected {} arguments but got {}.",
+                    function.arity(),
+                    arguments.len()
+                ),
+            ));
+        }
+        if self.call_depth == MAX_CALL_DEPTH || self.nesting >= MAX_NESTING {
+            return Err(RuntimeError::new(&call.paren, "Stack overflow."));
+        }
+
+        self.call_depth += 1;
+        let result = function.call(self, &call.paren, arguments);
+        self.call_depth -= 1;
+        result
+    }
+
+    fn visit_grouping(&mut self, _: ExprId, expr: ExprId) -> Result<Value, RuntimeError> {
+        self.evaluate(expr)
+    }
+
+    fn visit_literal(&mut self, _: ExprId, literal: &Literal) -> Result<Value, RuntimeError> {
+        Ok(literal.into())
+    }
+
+    // Evaluates the right operand only when the left one doesn't already
+    // decide the result, which is then returned as is rather than as a bool.
+    fn visit_logical(&mut self, _: ExprId, logical: &Logical) -> Result<Value, RuntimeError> {
+        let left = self.evaluate(logical.left)?;
+        let short_circuits = match logical.operator.token_type {
+            TokenType::Or => left.is_truthy(),
+            TokenType::And => !left.is_truthy(),
+            _ => unreachable!("parser only builds logical 'and' and 'or'"),
+        };
+        if short_circuits {
+            Ok(left)
+        } else {
+            self.evaluate(logical.right)
+        }
+    }
+
+    fn visit_unary(&mut self, _: ExprId, unary: &Unary) -> Result<Value, RuntimeError> {
+        let right = self.evaluate(unary.right)?;
+        match unary.operator.token_type {
+            TokenType::Bang => Ok(Value::Bool(!right.is_truthy())),
+            TokenType::Minus => match right {
+                Value::Number(number) => Ok(Value::Number(-number)),
+                _ => Err(RuntimeError::new(
+                    &unary.operator,
+                    "Operand must be a number.",
+                )),
+            },
+            _ => unreachable!("parser only builds unary '!' and '-'"),
+        }
+    }
+
+    fn visit_variable(&mut self, id: ExprId, name: &Token) -> Result<Value, RuntimeError> {
+        self.look_up_variable(name, id)
+    }
+}
+
+fn number_operands(
+    operator: &Token,
+    left: &Value,
+    right: &Value,
+) -> Result<(f64, f64), RuntimeError> {
+    match (left, right) {
+        (Value::Number(left), Value::Number(right)) => Ok((*left, *right)),
+        _ => Err(RuntimeError::new(operator, "Operands must be numbers.")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::callable::LoxCallable;
+    use crate::parser::Parser;
+    use crate::resolver;
+
+    fn run(interpreter: &mut Interpreter, source: &str) {
+        let (tokens, errors) = crate::tokenize(source);
+        assert!(errors.is_empty());
+        let mut program = Parser::new(tokens.into_iter()).parse().unwrap();
+        resolver::resolve(&mut program).unwrap();
+        interpreter.interpret(&Rc::new(program)).unwrap();
+    }
+
+    // Defines `keep`, which holds on to its argument weakly.
+    fn keeper(interpreter: &mut Interpreter) -> Rc<RefCell<Vec<Weak<dyn LoxCallable>>>> {
+        let kept = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&kept);
+        interpreter.define_native("keep", 1, move |arguments| {
+            if let Value::Callable(callable) = &arguments[0] {
+                sink.borrow_mut().push(Rc::downgrade(callable));
+            }
+            Ok(Value::Nil)
+        });
+        kept
+    }
+
+    #[test]
+    fn functions_out_of_reach_are_freed() {
+        let mut interpreter = Interpreter::new();
+        let kept = keeper(&mut interpreter);
+        run(
+            &mut interpreter,
+            "{ fun f() {} keep(f); }
+             fun outer() { var g; { fun h() { g; } g = h; } keep(g); }
+             outer();",
+        );
+        assert_eq!(kept.borrow().len(), 2);
+        assert!(kept.borrow().iter().all(|kept| kept.upgrade().is_none()));
+    }
+
+    #[test]
+    fn functions_in_reach_survive() {
+        let mut interpreter = Interpreter::new();
+        let kept = keeper(&mut interpreter);
+        run(
+            &mut interpreter,
+            "fun make() { var n = 0; fun count() { n = n + 1; return n; } return count; }
+             var counter = make();
+             keep(counter);
+             counter();",
+        );
+        run(&mut interpreter, "if (counter() != 2) undefined;");
+        assert!(kept.borrow()[0].upgrade().is_some());
+    }
+}
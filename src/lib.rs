@@ -0,0 +1,186 @@
+use interpreter::RuntimeError;
+use parser::ParseError;
+use resolver::ResolveError;
+use scanner::{ScanError, Scanner};
+use token::{Token, TokenType};
+
+pub mod ast_dot;
+pub mod ast_printer;
+pub mod callable;
+pub mod environment;
+pub mod expr;
+pub mod interpreter;
+pub mod parser;
+pub mod program;
+pub mod resolver;
+pub mod scanner;
+pub mod stmt;
+pub mod token;
+pub mod value;
+
+/// A lexical or syntax error, either of which stops a program before it
+/// runs.
+#[derive(Debug, Clone)]
+pub enum SyntaxError {
+    Lexical(ScanError),
+    Parse(ParseError),
+}
+
+impl SyntaxError {
+    pub fn report(&self) {
+        match self {
+            SyntaxError::Lexical(error) => error.report(),
+            SyntaxError::Parse(error) => error.report(),
+        }
+    }
+
+    fn start(&self) -> usize {
+        match self {
+            SyntaxError::Lexical(error) => error.span.start,
+            SyntaxError::Parse(error) => error.token.span.start,
+        }
+    }
+}
+
+/// The error that stopped a program.
+#[derive(Debug)]
+pub enum LoxError {
+    /// Lexical and syntax errors, in source order.
+    Syntax(Vec<SyntaxError>),
+    /// Resolution errors, in source order.
+    Resolve(Vec<ResolveError>),
+    Runtime(RuntimeError),
+}
+
+impl LoxError {
+    /// Merges the errors from scanning and parsing a program into source
+    /// order. A syntax error is often caused by a lexical one just before
+    /// it, so at the same place the lexical error comes first.
+    pub fn syntax(lexical: &[ScanError], parse: Vec<ParseError>) -> Self {
+        let mut errors: Vec<_> = lexical.iter().cloned().map(SyntaxError::Lexical).collect();
+        errors.extend(parse.into_iter().map(SyntaxError::Parse));
+        errors.sort_by_key(SyntaxError::start);
+        LoxError::Syntax(errors)
+    }
+
+    /// Prints the error on stderr.
+    pub fn report(&self) {
+        match self {
+            LoxError::Syntax(errors) => errors.iter().for_each(SyntaxError::report),
+            LoxError::Resolve(errors) => errors.iter().for_each(ResolveError::report),
+            LoxError::Runtime(error) => {
+                eprintln!("{}\n[line {}]", error.message, error.token.line_num);
+            }
+        }
+    }
+
+    /// Exit status for a script that stopped with this error, following
+    /// the BSD sysexits convention like the reference implementation.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LoxError::Syntax(_) | LoxError::Resolve(_) => 65,
+            LoxError::Runtime(_) => 70,
+        }
+    }
+}
+
+impl From<Vec<ResolveError>> for LoxError {
+    fn from(errors: Vec<ResolveError>) -> Self {
+        LoxError::Resolve(errors)
+    }
+}
+
+impl From<RuntimeError> for LoxError {
+    fn from(error: RuntimeError) -> Self {
+        LoxError::Runtime(error)
+    }
+}
+
+/// Scans `source` into owned tokens, each carrying its lexeme, literal,
+/// line and [`Span`](token::Span), ending with an `Eof` token. The
+/// characters of a lexical error are skipped, and the errors returned in
+/// source order alongside the tokens.
+pub fn tokenize(source: &str) -> (Vec<Token>, Vec<ScanError>) {
+    scan(Scanner::new(source.chars().collect()))
+}
+
+/// Like [`tokenize`], but keeps comments as
+/// [`Comment`](TokenType::Comment) tokens, as a syntax highlighter needs.
+pub fn tokenize_with_comments(source: &str) -> (Vec<Token>, Vec<ScanError>) {
+    scan(Scanner::new(source.chars().collect()).with_comments())
+}
+
+fn scan(mut scanner: Scanner) -> (Vec<Token>, Vec<ScanError>) {
+    let tokens = scanner.by_ref().collect();
+    (tokens, scanner.errors().to_vec())
+}
+
+pub(crate) fn error(line_num: usize, message: &str) {
+    report(line_num, "", message)
+}
+
+pub(crate) fn token_error(token: &Token, message: &str) {
+    if token.token_type == TokenType::Eof {
+        report(token.line_num, " at end", message);
+    } else {
+        report(
+            token.line_num,
+            format!(" at '{}'", token.lexeme).as_str(),
+            message,
+        );
+    }
+}
+
+fn report(line_num: usize, where_e: &str, message: &str) {
+    eprintln!("[line {line_num}] Error{where_e}: {message}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn types(tokens: &[Token]) -> Vec<TokenType> {
+        tokens.iter().map(|token| token.token_type).collect()
+    }
+
+    #[test]
+    fn tokenize_returns_tokens_and_errors() {
+        let (tokens, errors) = tokenize("var a = 1; // One.\n@ \"open");
+        assert_eq!(
+            types(&tokens),
+            [
+                TokenType::Var,
+                TokenType::Identifier,
+                TokenType::Equal,
+                TokenType::Number,
+                TokenType::Semicolon,
+                TokenType::Eof,
+            ]
+        );
+        let errors: Vec<(&str, usize)> = errors
+            .iter()
+            .map(|error| (error.message, error.line_num))
+            .collect();
+        assert_eq!(
+            errors,
+            [("Unexpected character.", 2), ("Unterminated string.", 2)]
+        );
+    }
+
+    #[test]
+    fn tokenize_with_comments_keeps_comments() {
+        let (tokens, errors) = tokenize_with_comments("var a; // One.\n");
+        assert!(errors.is_empty());
+        assert_eq!(
+            types(&tokens),
+            [
+                TokenType::Var,
+                TokenType::Identifier,
+                TokenType::Semicolon,
+                TokenType::Comment,
+                TokenType::Eof,
+            ]
+        );
+        assert_eq!(tokens[3].lexeme, "// One.");
+    }
+}
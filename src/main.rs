@@ -1,67 +1,341 @@
 use std::{
     env, fs,
-    io::{self, Write},
+    io::{self, BufRead, IsTerminal, Write},
+    process,
+    rc::Rc,
+    str, thread,
+    time::Instant,
 };
 
-use parser::Parser;
-use scanner::Scanner;
-use token::{Token, TokenType};
+use lox::{
+    ast_dot,
+    ast_printer::{self, Notation},
+    interpreter::Interpreter,
+    parser::Parser,
+    resolver,
+    scanner::Scanner,
+    token::Token,
+    LoxError,
+};
+
+// Terminal escape sequences for bracketed paste mode.
+const BRACKETED_PASTE_ON: &str = "\x1b[?2004h";
+const BRACKETED_PASTE_OFF: &str = "\x1b[?2004l";
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+
+const USAGE: &str = "\
+Usage: lox [options] [command]
+
+Commands:
+    run <script>       Run a script (same as 'lox <script>')
+    repl               Start an interactive prompt (the default)
+    tokens <script>    Print the tokens of a script, including comments
 
-mod expr;
-mod parser;
-mod scanner;
-mod token;
+Options:
+    --ast-dot          Print the AST as a Graphviz graph instead of running it
+    --print-ast[=rpn]  Print the AST as S-expressions, or with expressions in
+                       Reverse Polish Notation, instead of running it
+    --time             Report how long scanning, parsing and running took
+    -h, --help         Print this help
+    -V, --version      Print the version
+";
+
+// Parsing and interpreting recurse as deeply as the Lox code nests, which
+// in debug builds soon outgrows the main thread's stack.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+#[derive(Clone, Copy, Default)]
+struct Options {
+    // Print the AST as a Graphviz graph instead of running it.
+    ast_dot: bool,
+    // Print the AST as text instead of running it.
+    print_ast: Option<Notation>,
+    // Report the time spent in each phase on stderr.
+    time: bool,
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    match args.len() {
-        2 => run_file(args[1].as_str()),
-        1 => run_prompt(),
-        _ => println!("Usage: lox [script]"),
+    let cli = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(cli)
+        .unwrap();
+    if cli.join().is_err() {
+        process::exit(101);
+    }
+}
+
+fn cli() {
+    let mut options = Options::default();
+    let mut args = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--ast-dot" => options.ast_dot = true,
+            "--print-ast" | "--print-ast=lisp" => options.print_ast = Some(Notation::Lisp),
+            "--print-ast=rpn" => options.print_ast = Some(Notation::Rpn),
+            "--time" => options.time = true,
+            "-h" | "--help" => {
+                print!("{USAGE}");
+                return;
+            }
+            "-V" | "--version" => {
+                println!("lox {}", env!("CARGO_PKG_VERSION"));
+                return;
+            }
+            _ if arg.starts_with('-') => usage_error(&format!("unknown option '{arg}'")),
+            _ => args.push(arg),
+        }
+    }
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] | ["repl"] => run_prompt(options),
+        [command @ ("run" | "tokens")] => usage_error(&format!("'{command}' needs a script")),
+        ["run", path] | [path] => run_file(path, options),
+        ["tokens", path] => print_tokens(path),
+        _ => usage_error("too many arguments"),
     }
 }
 
-fn run_file(path: &str) {
-    let text = fs::read_to_string(path).unwrap();
-    run(&text);
+fn usage_error(message: &str) -> ! {
+    eprint!("lox: {message}\n\n{USAGE}");
+    process::exit(64);
+}
+
+fn run_file(path: &str, options: Options) {
+    let result = run(&read_source(path), options, &mut Interpreter::new());
+    if let Err(error) = result {
+        process::exit(error.exit_code());
+    }
 }
 
-fn run_prompt() {
-    loop {
+fn print_tokens(path: &str) {
+    let (tokens, errors) = lox::tokenize_with_comments(&read_source(path));
+    for token in tokens {
+        println!("{token}");
+    }
+    for error in &errors {
+        error.report();
+    }
+    if !errors.is_empty() {
+        process::exit(65);
+    }
+}
+
+/// Reads a script, exiting if it can't be opened or isn't text.
+fn read_source(path: &str) -> String {
+    load_source(path).unwrap_or_else(|(message, status)| {
+        eprintln!("{message}");
+        process::exit(status);
+    })
+}
+
+/// Reads a script, or says why it couldn't along with the exit status
+/// that calls for.
+fn load_source(path: &str) -> Result<String, (String, i32)> {
+    let bytes =
+        fs::read(path).map_err(|err| (format!("Could not open file \"{path}\": {err}"), 74))?;
+    decode_source(&bytes).map_err(|message| (format!("Could not read \"{path}\": {message}"), 65))
+}
+
+/// Decodes a script as UTF-8, dropping a byte order mark and turning
+/// Windows line endings into '\n'.
+fn decode_source(bytes: &[u8]) -> Result<String, &'static str> {
+    if bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(&[0xFE, 0xFF]) {
+        return Err("the file is UTF-16 encoded, save it as UTF-8");
+    }
+    let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+    let text = str::from_utf8(bytes).map_err(|_| "the file is not valid UTF-8 text")?;
+    Ok(text.replace("\r\n", "\n"))
+}
+
+fn run_prompt(options: Options) {
+    let bracketed_paste = BracketedPaste::enable();
+    let mut interpreter = Interpreter::new();
+    // Inputs that ran without errors, written out by `:save`.
+    let mut history: Vec<String> = Vec::new();
+    let status = loop {
         print!("> ");
-        io::stdout().flush().unwrap();
+        if io::stdout().flush().is_err() {
+            // Nowhere to show output anymore, e.g. a closed pipe.
+            break 74;
+        }
         let mut line = String::new();
-        io::stdin().read_line(&mut line).unwrap();
-        if !line.is_empty() {
-            run(&line);
+        match read_input(&mut io::stdin().lock(), &mut line) {
+            // End of input, e.g. Ctrl-D.
+            Ok(0) => {
+                println!();
+                break 0;
+            }
+            Ok(_) => {}
+            // The offending line has been consumed, so carry on with the
+            // next one.
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                eprintln!("Could not read input: {err}");
+                continue;
+            }
+            Err(err) => {
+                eprintln!("Could not read input: {err}");
+                break 74;
+            }
+        }
+        if line.trim().is_empty() {
+            continue;
         }
+
+        if matches!(line.trim(), ":save" | ":replay") {
+            eprintln!("Usage: {} <path>", line.trim());
+        } else if let Some(path) = line.trim().strip_prefix(":save ") {
+            save_session(path.trim(), &history);
+        } else if let Some(path) = line.trim().strip_prefix(":replay ") {
+            replay_session(path.trim(), &mut history, options, &mut interpreter);
+        } else if run(&line, options, &mut interpreter).is_ok() {
+            history.push(line.trim_end().to_string());
+        }
+    };
+    drop(bracketed_paste);
+    if status != 0 {
+        process::exit(status);
     }
 }
 
-fn run(source: &str) {
-    let mut scanner = Scanner::new(source.chars().collect());
-    let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(tokens);
-    let expr = parser.parse();
-    println!("{:#?}", expr);
+// Bracketed paste mode, on for as long as this lives. It's turned off
+// again when dropped, so that also happens when the REPL panics.
+struct BracketedPaste;
+
+impl BracketedPaste {
+    // Only when talking to a terminal both ways, since the escape sequences
+    // would otherwise end up in redirected output.
+    fn enable() -> Option<Self> {
+        if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+            return None;
+        }
+        print!("{BRACKETED_PASTE_ON}");
+        Some(BracketedPaste)
+    }
+}
+
+impl Drop for BracketedPaste {
+    fn drop(&mut self) {
+        // Writing can't be allowed to panic, this may run during a panic.
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "{BRACKETED_PASTE_OFF}");
+        let _ = stdout.flush();
+    }
+}
+
+// Reads one REPL input. A bracketed paste is read through to its end
+// marker, so a pasted multi-line program runs as a single input.
+fn read_input(input: &mut impl BufRead, line: &mut String) -> io::Result<usize> {
+    let mut read = input.read_line(line)?;
+    if let Some(start) = line.find(PASTE_START) {
+        line.replace_range(start..start + PASTE_START.len(), "");
+        while !line.contains(PASTE_END) {
+            match input.read_line(line)? {
+                0 => break,
+                n => read += n,
+            }
+        }
+        *line = line.replacen(PASTE_END, "", 1);
+    }
+    Ok(read)
+}
+
+fn save_session(path: &str, history: &[String]) {
+    let mut text = history.join("\n");
+    text.push('\n');
+    if let Err(err) = fs::write(path, text) {
+        eprintln!("Could not write \"{path}\": {err}");
+    }
 }
 
-fn error(line_num: usize, message: &str) {
-    report(line_num, "", message)
+fn replay_session(
+    path: &str,
+    history: &mut Vec<String>,
+    options: Options,
+    interpreter: &mut Interpreter,
+) {
+    match load_source(path) {
+        Ok(text) => {
+            if run(&text, options, interpreter).is_ok() {
+                history.push(text.trim_end().to_string());
+            }
+        }
+        Err((message, _)) => eprintln!("{message}"),
+    }
 }
 
-fn token_error(token: Token, message: &str) {
-    if token.token_type == TokenType::Eof {
-        report(token.line_num, " at end", message);
+/// Runs `source`, reporting the error that stopped it, if any.
+fn run(source: &str, options: Options, interpreter: &mut Interpreter) -> Result<(), LoxError> {
+    let mut scanner = Scanner::new(source.chars().collect());
+    // Tokens are normally scanned as the parser asks for them. Timing
+    // scans them all up front so the phases can be measured separately.
+    let start = Instant::now();
+    let tokens: Box<dyn Iterator<Item = Token>> = if options.time {
+        Box::new(scanner.by_ref().collect::<Vec<_>>().into_iter())
     } else {
-        report(
-            token.line_num,
-            format!(" at '{}'", token.lexeme).as_str(),
-            message,
-        );
+        Box::new(scanner.by_ref())
+    };
+    let scanned = Instant::now();
+
+    let mut parser = Parser::new(tokens);
+    let program = parser.parse();
+    let parsed = Instant::now();
+
+    // Finish the scan so every lexical error gets reported, even those
+    // past where the parser stopped.
+    drop(parser);
+    scanner.by_ref().for_each(drop);
+
+    let result = match program {
+        Err(errors) => Err(LoxError::syntax(scanner.errors(), errors)),
+        Ok(_) if !scanner.errors().is_empty() => {
+            Err(LoxError::syntax(scanner.errors(), Vec::new()))
+        }
+        Ok(program) if options.ast_dot || options.print_ast.is_some() => {
+            if options.ast_dot {
+                print!("{}", ast_dot::to_dot(&program));
+            }
+            if let Some(notation) = options.print_ast {
+                print!("{}", ast_printer::print(&program, notation));
+            }
+            Ok(())
+        }
+        Ok(mut program) => resolver::resolve(&mut program)
+            .map_err(LoxError::from)
+            .and_then(|()| {
+                interpreter
+                    .interpret(&Rc::new(program))
+                    .map_err(LoxError::from)
+            }),
+    };
+    if let Err(error) = &result {
+        error.report();
     }
+    if options.time {
+        let finished = Instant::now();
+        eprintln!("scan   {:?}", scanned - start);
+        eprintln!("parse  {:?}", parsed - scanned);
+        eprintln!("run    {:?}", finished - parsed);
+    }
+    result
 }
 
-fn report(line_num: usize, where_e: &str, message: &str) {
-    eprintln!("[line {line_num}] Error{where_e}: {message}");
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_input_joins_a_bracketed_paste() {
+        let mut input = io::Cursor::new(format!(
+            "{PASTE_START}var a = 1;\nprint a;\nprint a + 1;{PASTE_END}\nprint 3;\n"
+        ));
+        let mut line = String::new();
+        read_input(&mut input, &mut line).unwrap();
+        assert_eq!(line, "var a = 1;\nprint a;\nprint a + 1;\n");
+
+        line.clear();
+        read_input(&mut input, &mut line).unwrap();
+        assert_eq!(line, "print 3;\n");
+    }
 }
@@ -1,41 +1,489 @@
-use crate::expr::{Binary, Expr, Literal, Unary};
-use crate::token::{Token, TokenType};
+use std::mem;
+use std::rc::Rc;
+
+use crate::expr::{Assign, Binary, Call, Expr, ExprArena, ExprId, Literal, Logical, Unary};
+use crate::program::Program;
+use crate::stmt::{Function, If, Return, Stmt, StmtKind, Var, While};
+use crate::token::{Span, Token, TokenType};
 use crate::token_error;
 
-pub struct Parser {
-    tokens: Vec<Token>,
-    current: usize,
+// How deeply expressions may nest before the parser gives up, so that
+// adversarial input can't overflow the host stack.
+const MAX_DEPTH: usize = 1000;
+
+// Most arguments a call may pass, and so parameters a function may take.
+const MAX_ARGUMENTS: usize = 255;
+
+/// A syntax error, found at `token`.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub token: Token,
+    pub message: String,
 }
 
-impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, current: 0 }
+impl ParseError {
+    pub fn report(&self) {
+        token_error(&self.token, &self.message);
     }
+}
 
-    pub fn parse(&mut self) -> Expr {
-        self.expression()
+pub struct Parser<I: Iterator<Item = Token>> {
+    tokens: I,
+    arena: ExprArena,
+    current: Token,
+    previous: Token,
+    depth: usize,
+    comments: Vec<Token>,
+    // Syntax errors found so far, in source order.
+    errors: Vec<ParseError>,
+    // Set once the depth limit is hit. Recovering from that would only
+    // report it again at every level, so the parser stops there.
+    gave_up: bool,
+    // How many loops enclose the current statement within its function,
+    // to reject `break` and `continue` outside of one.
+    loop_depth: usize,
+}
+
+impl<I: Iterator<Item = Token>> Parser<I> {
+    pub fn new(tokens: I) -> Self {
+        let mut parser = Parser {
+            tokens,
+            arena: ExprArena::default(),
+            current: Token::default(),
+            previous: Token::default(),
+            depth: 0,
+            comments: Vec::new(),
+            errors: Vec::new(),
+            gave_up: false,
+            loop_depth: 0,
+        };
+        parser.current = parser.next_token();
+        parser
     }
 
-    // expression     → equality ;
-    fn expression(&mut self) -> Expr {
-        self.equality()
+    // program        → declaration* EOF ;
+    //
+    // Parsing carries on past a syntax error so later ones get reported
+    // too, but the program as a whole is then rejected.
+    pub fn parse(&mut self) -> Result<Program, Vec<ParseError>> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            statements.extend(self.declaration());
+        }
+        if !self.errors.is_empty() {
+            return Err(mem::take(&mut self.errors));
+        }
+        Ok(Program::new(statements, mem::take(&mut self.arena)))
     }
 
-    // equality       → comparison ( ( "!=" | "==" ) comparison )* ;
-    fn equality(&mut self) -> Expr {
-        let mut expr = self.comparison();
-        while self.match_type(&[TokenType::BangEqual, TokenType::EqualEqual]) {
-            let operator = self.previous();
-            let right = self.comparison();
-            expr = Expr::Binary(Binary {
-                left: Box::new(expr),
+    /// Comment tokens seen so far, in source order. They are only present
+    /// when the scanner was built with [`Scanner::with_comments`], and can
+    /// be matched to AST nodes through their spans.
+    ///
+    /// [`Scanner::with_comments`]: crate::scanner::Scanner::with_comments
+    pub fn comments(&self) -> &[Token] {
+        &self.comments
+    }
+
+    // declaration    → funDecl
+    //                | varDecl
+    //                | statement ;
+    //
+    // A declaration with a syntax error is skipped, up to where the next
+    // one likely starts.
+    fn declaration(&mut self) -> Option<Stmt> {
+        let declaration = if self.match_type(&[TokenType::Fun]) {
+            self.function("function")
+        } else if self.match_type(&[TokenType::Var]) {
+            self.var_declaration()
+        } else {
+            self.statement()
+        };
+        match declaration {
+            Ok(declaration) => Some(declaration),
+            Err(_) => {
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    // funDecl        → "fun" function ;
+    // function       → IDENTIFIER "(" parameters? ")" block ;
+    // parameters     → IDENTIFIER ( "," IDENTIFIER )* ;
+    fn function(&mut self, kind: &str) -> Result<Stmt, ParseError> {
+        let start = self.previous().span;
+        let name = self.consume(TokenType::Identifier, &format!("Expect {kind} name."))?;
+        self.consume(
+            TokenType::LeftParen,
+            &format!("Expect '(' after {kind} name."),
+        )?;
+        let mut params = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if params.len() == MAX_ARGUMENTS {
+                    self.error(
+                        &self.peek().clone(),
+                        &format!("Can't have more than {MAX_ARGUMENTS} parameters."),
+                    );
+                }
+                params.push(self.consume(TokenType::Identifier, "Expect parameter name.")?);
+                if !self.match_type(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            &format!("Expect '{{' before {kind} body."),
+        )?;
+        // A loop around the declaration doesn't reach into the body.
+        let loop_depth = mem::take(&mut self.loop_depth);
+        let body = self.nested("Block too deeply nested.", Self::block);
+        self.loop_depth = loop_depth;
+        let body = body?;
+        let function = Rc::new(Function { name, params, body });
+        Ok(self.finish(StmtKind::Function(function), start))
+    }
+
+    // varDecl        → "var" IDENTIFIER ( "=" expression )? ";" ;
+    fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.previous().span;
+        let name = self.consume(TokenType::Identifier, "Expect variable name.")?;
+        let mut initializer = None;
+        if self.match_type(&[TokenType::Equal]) {
+            initializer = Some(self.expression()?);
+        }
+        self.consume(
+            TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        )?;
+        Ok(self.finish(StmtKind::Var(Var { name, initializer }), start))
+    }
+
+    // statement      → breakStmt
+    //                | continueStmt
+    //                | exprStmt
+    //                | forStmt
+    //                | ifStmt
+    //                | printStmt
+    //                | returnStmt
+    //                | whileStmt
+    //                | block ;
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_type(&[TokenType::Break, TokenType::Continue]) {
+            return self.loop_jump();
+        }
+        if self.match_type(&[TokenType::For]) {
+            return self.for_statement();
+        }
+        if self.match_type(&[TokenType::If]) {
+            return self.if_statement();
+        }
+        if self.match_type(&[TokenType::Print]) {
+            return self.print_statement();
+        }
+        if self.match_type(&[TokenType::Return]) {
+            return self.return_statement();
+        }
+        if self.match_type(&[TokenType::While]) {
+            return self.while_statement();
+        }
+        if self.match_type(&[TokenType::LeftBrace]) {
+            let start = self.previous().span;
+            let statements = self.nested("Block too deeply nested.", Self::block)?;
+            return Ok(self.finish(StmtKind::Block(statements), start));
+        }
+        self.expression_statement()
+    }
+
+    // breakStmt      → "break" ";" ;
+    // continueStmt   → "continue" ";" ;
+    fn loop_jump(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        if self.loop_depth == 0 {
+            // Report but don't bail out, the parser isn't confused.
+            self.error(
+                &keyword,
+                &format!("Can't use '{}' outside of a loop.", keyword.lexeme),
+            );
+        }
+        self.consume(
+            TokenType::Semicolon,
+            &format!("Expect ';' after '{}'.", keyword.lexeme),
+        )?;
+        let start = keyword.span;
+        let kind = match keyword.token_type {
+            TokenType::Break => StmtKind::Break(keyword),
+            _ => StmtKind::Continue(keyword),
+        };
+        Ok(self.finish(kind, start))
+    }
+
+    // forStmt        → "for" "(" ( varDecl | exprStmt | ";" )
+    //                  expression? ";"
+    //                  expression? ")" statement ;
+    //
+    // Desugared into a while loop, inside a block if there's an initializer.
+    fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.previous().span;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+        let initializer = if self.match_type(&[TokenType::Semicolon]) {
+            None
+        } else if self.match_type(&[TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+
+        let condition = if self.check(TokenType::Semicolon) {
+            let span = self.peek().span;
+            self.arena.alloc(Expr::Literal(Literal::Bool(true)), span)
+        } else {
+            self.expression()?
+        };
+        self.consume(TokenType::Semicolon, "Expect ';' after loop condition.")?;
+
+        let mut increment = None;
+        if !self.check(TokenType::RightParen) {
+            increment = Some(self.expression()?);
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        let body = Box::new(self.loop_body()?);
+        let mut desugared = self.finish(
+            StmtKind::While(While {
+                condition,
+                body,
+                increment,
+            }),
+            start,
+        );
+        if let Some(initializer) = initializer {
+            desugared = self.finish(StmtKind::Block(vec![initializer, desugared]), start);
+        }
+        Ok(desugared)
+    }
+
+    // ifStmt         → "if" "(" expression ")" statement
+    //                  ( "else" statement )? ;
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.previous().span;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch = Box::new(self.body()?);
+        let mut else_branch = None;
+        if self.match_type(&[TokenType::Else]) {
+            else_branch = Some(Box::new(self.body()?));
+        }
+        Ok(self.finish(
+            StmtKind::If(If {
+                condition,
+                then_branch,
+                else_branch,
+            }),
+            start,
+        ))
+    }
+
+    // whileStmt      → "while" "(" expression ")" statement ;
+    fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.previous().span;
+        self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
+        let body = Box::new(self.loop_body()?);
+        Ok(self.finish(
+            StmtKind::While(While {
+                condition,
+                body,
+                increment: None,
+            }),
+            start,
+        ))
+    }
+
+    // The statement nested in an if, while or for.
+    fn body(&mut self) -> Result<Stmt, ParseError> {
+        self.nested("Statement too deeply nested.", Self::statement)
+    }
+
+    fn loop_body(&mut self) -> Result<Stmt, ParseError> {
+        self.loop_depth += 1;
+        let body = self.body();
+        self.loop_depth -= 1;
+        body
+    }
+
+    // printStmt      → "print" expression ";" ;
+    fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.previous().span;
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
+        Ok(self.finish(StmtKind::Print(value), start))
+    }
+
+    // returnStmt     → "return" expression? ";" ;
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let mut value = None;
+        if !self.check(TokenType::Semicolon) {
+            value = Some(self.expression()?);
+        }
+        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
+        let start = keyword.span;
+        Ok(self.finish(StmtKind::Return(Return { keyword, value }), start))
+    }
+
+    // block          → "{" declaration* "}" ;
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            statements.extend(self.declaration());
+        }
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
+    // exprStmt       → expression ";" ;
+    fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expr = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after expression.")?;
+        let start = self.arena.span(expr);
+        Ok(self.finish(StmtKind::Expression(expr), start))
+    }
+
+    // Builds a statement spanning from `start` through the token just
+    // consumed.
+    fn finish(&self, kind: StmtKind, start: Span) -> Stmt {
+        Stmt {
+            kind,
+            span: start.to(self.previous().span),
+        }
+    }
+
+    // expression     → assignment ;
+    fn expression(&mut self) -> Result<ExprId, ParseError> {
+        self.nested("Expression too deeply nested.", Self::assignment)
+    }
+
+    // assignment     → IDENTIFIER "=" assignment
+    //                | logic_or ;
+    fn assignment(&mut self) -> Result<ExprId, ParseError> {
+        let expr = self.or()?;
+
+        if self.match_type(&[TokenType::Equal]) {
+            let equals = self.previous().clone();
+            let value = self.nested("Expression too deeply nested.", Self::assignment)?;
+
+            if let Expr::Variable(name) = &self.arena[expr] {
+                let name = name.clone();
+                let span = name.span.to(self.arena.span(value));
+                return Ok(self.arena.alloc(Expr::Assign(Assign { name, value }), span));
+            }
+
+            // Report but don't bail out, the parser isn't confused.
+            self.error(&equals, "Invalid assignment target.");
+        }
+
+        Ok(expr)
+    }
+
+    // logic_or       → logic_and ( "or" logic_and )* ;
+    fn or(&mut self) -> Result<ExprId, ParseError> {
+        self.chain(&[TokenType::Or], Self::and, Self::logical)
+    }
+
+    // logic_and      → equality ( "and" equality )* ;
+    fn and(&mut self) -> Result<ExprId, ParseError> {
+        self.chain(&[TokenType::And], Self::equality, Self::logical)
+    }
+
+    fn logical(&mut self, left: ExprId, operator: Token, right: ExprId) -> ExprId {
+        let span = self.arena.span(left).to(self.arena.span(right));
+        self.arena.alloc(
+            Expr::Logical(Logical {
+                left,
                 operator,
-                right: Box::new(right),
-            });
+                right,
+            }),
+            span,
+        )
+    }
+
+    fn binary(&mut self, left: ExprId, operator: Token, right: ExprId) -> ExprId {
+        let span = self.arena.span(left).to(self.arena.span(right));
+        self.arena.alloc(
+            Expr::Binary(Binary {
+                left,
+                operator,
+                right,
+            }),
+            span,
+        )
+    }
+
+    // Parses a left-associative chain like `a + b + c`. The chain is built
+    // in a loop, but every operator makes the tree one level taller, so
+    // each counts toward the depth limit like a nested expression does.
+    fn chain(
+        &mut self,
+        operators: &[TokenType],
+        operand: fn(&mut Self) -> Result<ExprId, ParseError>,
+        combine: fn(&mut Self, ExprId, Token, ExprId) -> ExprId,
+    ) -> Result<ExprId, ParseError> {
+        let depth = self.depth;
+        let mut expr = operand(self);
+        while let Ok(left) = expr {
+            if !self.match_type(operators) {
+                break;
+            }
+            let operator = self.previous().clone();
+            expr = self
+                .descend("Expression too deeply nested.")
+                .and_then(|()| operand(self))
+                .map(|right| combine(self, left, operator, right));
         }
+        self.depth = depth;
         expr
     }
 
+    fn nested<T>(
+        &mut self,
+        message: &str,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        self.descend(message)?;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    // Goes one level deeper, unless that's past the depth limit.
+    fn descend(&mut self, message: &str) -> Result<(), ParseError> {
+        if self.depth == MAX_DEPTH {
+            let error = self.error(&self.peek().clone(), message);
+            self.gave_up = true;
+            return Err(error);
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    // equality       → comparison ( ( "!=" | "==" ) comparison )* ;
+    fn equality(&mut self) -> Result<ExprId, ParseError> {
+        self.chain(
+            &[TokenType::BangEqual, TokenType::EqualEqual],
+            Self::comparison,
+            Self::binary,
+        )
+    }
+
     fn match_type(&mut self, token_types: &[TokenType]) -> bool {
         for token_type in token_types {
             if self.check(*token_type) {
@@ -54,125 +502,214 @@ impl Parser {
         self.peek().token_type == token_type
     }
 
-    fn advance(&mut self) -> Token {
+    fn advance(&mut self) -> &Token {
         if !self.is_at_end() {
-            self.current += 1;
+            let next = self.next_token();
+            self.previous = mem::replace(&mut self.current, next);
         }
         self.previous()
     }
 
+    // Pulls the next token from the scanner, setting comments aside.
+    fn next_token(&mut self) -> Token {
+        loop {
+            match self.tokens.next() {
+                Some(token) if token.token_type == TokenType::Comment => self.comments.push(token),
+                Some(token) => return token,
+                None => {
+                    let end = self.current.span.end;
+                    return Token {
+                        token_type: TokenType::Eof,
+                        line_num: self.current.line_num,
+                        span: Span { start: end, end },
+                        ..Default::default()
+                    };
+                }
+            }
+        }
+    }
+
     fn is_at_end(&self) -> bool {
         self.peek().token_type == TokenType::Eof
     }
 
-    fn peek(&self) -> Token {
-        self.tokens[self.current].clone()
+    fn peek(&self) -> &Token {
+        &self.current
     }
 
-    fn previous(&self) -> Token {
-        self.tokens[self.current - 1].clone()
+    fn previous(&self) -> &Token {
+        &self.previous
     }
 
     // comparison     → term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
-    fn comparison(&mut self) -> Expr {
-        let mut expr = self.term();
-        while self.match_type(&[
-            TokenType::Greater,
-            TokenType::GreaterEqual,
-            TokenType::Less,
-            TokenType::LessEqual,
-        ]) {
-            let operator = self.previous();
-            let right = self.term();
-            expr = Expr::Binary(Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            });
-        }
-        expr
+    fn comparison(&mut self) -> Result<ExprId, ParseError> {
+        self.chain(
+            &[
+                TokenType::Greater,
+                TokenType::GreaterEqual,
+                TokenType::Less,
+                TokenType::LessEqual,
+            ],
+            Self::term,
+            Self::binary,
+        )
     }
 
     // term           → factor ( ( "-" | "+" ) factor )* ;
-    fn term(&mut self) -> Expr {
-        let mut expr = self.factor();
-        while self.match_type(&[TokenType::Minus, TokenType::Plus]) {
-            let operator = self.previous();
-            let right = self.factor();
-            expr = Expr::Binary(Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            });
-        }
-        expr
+    fn term(&mut self) -> Result<ExprId, ParseError> {
+        self.chain(
+            &[TokenType::Minus, TokenType::Plus],
+            Self::factor,
+            Self::binary,
+        )
     }
 
     // factor         → unary ( ( "/" | "*" ) unary )* ;
-    fn factor(&mut self) -> Expr {
-        let mut expr = self.unary();
-        while self.match_type(&[TokenType::Slash, TokenType::Star]) {
-            let operator = self.previous();
-            let right = self.unary();
-            expr = Expr::Binary(Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            });
-        }
-        expr
+    fn factor(&mut self) -> Result<ExprId, ParseError> {
+        self.chain(
+            &[TokenType::Slash, TokenType::Star],
+            Self::unary,
+            Self::binary,
+        )
     }
 
     // unary          → ( "!" | "-" ) unary
-    //                | primary ;
-    fn unary(&mut self) -> Expr {
+    //                | call ;
+    fn unary(&mut self) -> Result<ExprId, ParseError> {
         if self.match_type(&[TokenType::Bang, TokenType::Minus]) {
-            let operator = self.previous();
-            let right = self.unary();
-            return Expr::Unary(Unary {
-                operator,
-                right: Box::new(right),
-            });
+            let operator = self.previous().clone();
+            let right = self.nested("Expression too deeply nested.", Self::unary)?;
+            let span = operator.span.to(self.arena.span(right));
+            return Ok(self
+                .arena
+                .alloc(Expr::Unary(Unary { operator, right }), span));
+        }
+        self.call()
+    }
+
+    // call           → primary ( "(" arguments? ")" )* ;
+    //
+    // Like an operator chain, every call makes the tree one level taller.
+    fn call(&mut self) -> Result<ExprId, ParseError> {
+        let depth = self.depth;
+        let mut expr = self.primary();
+        while let Ok(callee) = expr {
+            if !self.match_type(&[TokenType::LeftParen]) {
+                break;
+            }
+            expr = self
+                .descend("Expression too deeply nested.")
+                .and_then(|()| self.finish_call(callee));
+        }
+        self.depth = depth;
+        expr
+    }
+
+    // arguments      → expression ( "," expression )* ;
+    fn finish_call(&mut self, callee: ExprId) -> Result<ExprId, ParseError> {
+        let mut arguments = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if arguments.len() == MAX_ARGUMENTS {
+                    // Report but don't bail out, the parser isn't confused.
+                    self.error(
+                        &self.peek().clone(),
+                        &format!("Can't have more than {MAX_ARGUMENTS} arguments."),
+                    );
+                }
+                arguments.push(self.expression()?);
+                if !self.match_type(&[TokenType::Comma]) {
+                    break;
+                }
+            }
         }
-        self.primary()
+        let paren = self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
+        let span = self.arena.span(callee).to(paren.span);
+        Ok(self.arena.alloc(
+            Expr::Call(Call {
+                callee,
+                paren,
+                arguments,
+            }),
+            span,
+        ))
     }
 
     // primary        → NUMBER | STRING | "true" | "false" | "nil"
-    //                | "(" expression ")" ;
-    fn primary(&mut self) -> Expr {
+    //                | "(" expression ")"
+    //                | IDENTIFIER ;
+    fn primary(&mut self) -> Result<ExprId, ParseError> {
         if self.match_type(&[TokenType::False]) {
-            return Expr::Literal(Literal::Bool(false));
+            return Ok(self
+                .arena
+                .alloc(Expr::Literal(Literal::Bool(false)), self.previous().span));
         }
         if self.match_type(&[TokenType::True]) {
-            return Expr::Literal(Literal::Bool(true));
+            return Ok(self
+                .arena
+                .alloc(Expr::Literal(Literal::Bool(true)), self.previous().span));
         }
         if self.match_type(&[TokenType::Nil]) {
-            return Expr::Literal(Literal::Nil);
+            return Ok(self
+                .arena
+                .alloc(Expr::Literal(Literal::Nil), self.previous().span));
         }
 
         if self.match_type(&[TokenType::Number, TokenType::String]) {
-            return Expr::Literal(self.previous().literal);
+            let token = self.previous();
+            let (literal, span) = (token.literal.clone(), token.span);
+            return Ok(self.arena.alloc(Expr::Literal(literal), span));
+        }
+
+        if self.match_type(&[TokenType::Identifier]) {
+            let name = self.previous().clone();
+            let span = name.span;
+            return Ok(self.arena.alloc(Expr::Variable(name), span));
         }
 
         if self.match_type(&[TokenType::LeftParen]) {
-            let expr = self.expression();
-            self.consume(TokenType::RightParen, "Except ')' after expression.")
-                .unwrap();
-            return Expr::Grouping(Box::new(expr));
+            let left_paren = self.previous().span;
+            let expr = self.expression()?;
+            let right_paren = self
+                .consume(TokenType::RightParen, "Except ')' after expression.")?
+                .span;
+            return Ok(self
+                .arena
+                .alloc(Expr::Grouping(expr), left_paren.to(right_paren)));
         }
 
-        panic!("{:#?}", token_error(self.peek(), "Expect expression."));
+        Err(self.error(&self.peek().clone(), "Expect expression."))
     }
 
-    fn consume<'a>(&mut self, token_type: TokenType, message: &'a str) -> Result<Token, &'a str> {
+    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, ParseError> {
         if self.check(token_type) {
-            return Ok(self.advance());
+            return Ok(self.advance().clone());
+        }
+        Err(self.error(&self.peek().clone(), message))
+    }
+
+    // Records a syntax error, which rejects the whole program even if the
+    // parser carries on without bailing out.
+    fn error(&mut self, token: &Token, message: &str) -> ParseError {
+        let error = ParseError {
+            token: token.clone(),
+            message: message.to_string(),
+        };
+        if !self.gave_up {
+            self.errors.push(error.clone());
         }
-        token_error(self.peek(), message);
-        Err(message)
+        error
     }
 
+    // Skips tokens until a statement boundary: just past a semicolon or
+    // before a keyword that starts a statement.
     fn synchronize(&mut self) {
+        if self.gave_up {
+            while !self.is_at_end() {
+                self.advance();
+            }
+            return;
+        }
         self.advance();
 
         while !self.is_at_end() {
@@ -196,3 +733,39 @@ impl Parser {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn comments_are_kept_in_source_order() {
+        let source = "// Greets.\nprint \"hi\"; // Inline.\n/* Block. */ var a;";
+        let scanner = Scanner::new(source.chars().collect()).with_comments();
+        let mut parser = Parser::new(scanner);
+        let program = parser.parse().unwrap();
+        assert_eq!(program.statements().len(), 2);
+
+        let comments: Vec<(&str, usize, &str)> = parser
+            .comments()
+            .iter()
+            .map(|comment| {
+                let span = comment.span;
+                (
+                    comment.lexeme.as_str(),
+                    comment.line_num,
+                    &source[span.start..span.end],
+                )
+            })
+            .collect();
+        assert_eq!(
+            comments,
+            [
+                ("// Greets.", 1, "// Greets."),
+                ("// Inline.", 2, "// Inline."),
+                ("/* Block. */", 3, "/* Block. */"),
+            ]
+        );
+    }
+}
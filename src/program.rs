@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use std::ops::Index;
+
+use crate::expr::{Expr, ExprArena, ExprId};
+use crate::stmt::Stmt;
+
+/// A parsed program: its statements and the arena their expressions live
+/// in, kept together so they can't be paired with another program's.
+/// Functions hold on to the program they were declared in.
+#[derive(Debug, Default)]
+pub struct Program {
+    statements: Vec<Stmt>,
+    arena: ExprArena,
+    // How many scopes out each local variable reference is, filled in by
+    // the resolver.
+    locals: HashMap<ExprId, usize>,
+}
+
+impl Program {
+    pub(crate) fn new(statements: Vec<Stmt>, arena: ExprArena) -> Self {
+        Program {
+            statements,
+            arena,
+            locals: HashMap::new(),
+        }
+    }
+
+    pub fn statements(&self) -> &[Stmt] {
+        &self.statements
+    }
+
+    pub fn arena(&self) -> &ExprArena {
+        &self.arena
+    }
+
+    pub(crate) fn set_locals(&mut self, locals: HashMap<ExprId, usize>) {
+        self.locals = locals;
+    }
+
+    /// How many scopes out from its use the variable in `expr` lives, or
+    /// `None` for a global.
+    pub(crate) fn depth(&self, expr: ExprId) -> Option<usize> {
+        self.locals.get(&expr).copied()
+    }
+}
+
+impl Index<ExprId> for Program {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.arena[id]
+    }
+}
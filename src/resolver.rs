@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::expr::{Assign, Binary, Call, ExprArena, ExprId, ExprVisitor, Literal, Logical, Unary};
+use crate::program::Program;
+use crate::stmt::{Function, If, Return, Stmt, StmtVisitor, Var, While};
+use crate::token::Token;
+use crate::token_error;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+}
+
+/// A resolution error, found at `token`.
+#[derive(Debug, Clone)]
+pub struct ResolveError {
+    pub token: Token,
+    pub message: String,
+}
+
+impl ResolveError {
+    pub fn report(&self) {
+        token_error(&self.token, &self.message);
+    }
+}
+
+/// Works out, before the program runs, which scope every local variable
+/// reference binds to, and records it in the program for the interpreter.
+/// Misuses of variables and `return` are collected along the way, in source
+/// order, and reject the program like syntax errors do.
+pub fn resolve(program: &mut Program) -> Result<(), Vec<ResolveError>> {
+    let mut resolver = Resolver {
+        arena: program.arena(),
+        scopes: Vec::new(),
+        current_function: FunctionType::None,
+        locals: HashMap::new(),
+        errors: Vec::new(),
+    };
+    resolver.resolve(program.statements());
+    if !resolver.errors.is_empty() {
+        return Err(resolver.errors);
+    }
+    let locals = resolver.locals;
+    program.set_locals(locals);
+    Ok(())
+}
+
+struct Resolver<'a> {
+    arena: &'a ExprArena,
+    // Local scopes, innermost last. A name maps to whether its initializer
+    // has been resolved, so a variable can't be read in its own.
+    scopes: Vec<HashMap<String, bool>>,
+    current_function: FunctionType,
+    // How many scopes out each resolved local variable reference is.
+    locals: HashMap<ExprId, usize>,
+    errors: Vec<ResolveError>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.stmt(statement);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        stmt.accept(self)
+    }
+
+    fn expr(&mut self, id: ExprId) {
+        id.accept(self.arena, self)
+    }
+
+    fn function(&mut self, function: &Function, function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+        self.begin_scope();
+        for param in &function.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve(&function.body);
+        self.end_scope();
+        self.current_function = enclosing_function;
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.errors.push(ResolveError {
+            token: token.clone(),
+            message: message.to_string(),
+        });
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    // Globals aren't tracked, redeclaring one is allowed.
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+        if scope.insert(name.lexeme.clone(), false).is_some() {
+            self.error(name, "Already a variable with this name in this scope.");
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), true);
+        }
+    }
+
+    // Names not found in any local scope are left to be looked up as
+    // globals.
+    fn resolve_local(&mut self, expr: ExprId, name: &Token) {
+        if let Some(depth) = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&name.lexeme))
+        {
+            self.locals.insert(expr, depth);
+        }
+    }
+}
+
+impl StmtVisitor<()> for Resolver<'_> {
+    fn visit_block(&mut self, statements: &[Stmt]) {
+        self.begin_scope();
+        self.resolve(statements);
+        self.end_scope();
+    }
+
+    fn visit_break(&mut self, _: &Token) {}
+
+    fn visit_continue(&mut self, _: &Token) {}
+
+    fn visit_expression(&mut self, expr: ExprId) {
+        self.expr(expr);
+    }
+
+    fn visit_function(&mut self, function: &Rc<Function>) {
+        // Declared before the body so it can call itself.
+        self.declare(&function.name);
+        self.define(&function.name);
+        self.function(function, FunctionType::Function);
+    }
+
+    fn visit_if(&mut self, if_stmt: &If) {
+        self.expr(if_stmt.condition);
+        self.stmt(&if_stmt.then_branch);
+        if let Some(else_branch) = &if_stmt.else_branch {
+            self.stmt(else_branch);
+        }
+    }
+
+    fn visit_print(&mut self, expr: ExprId) {
+        self.expr(expr);
+    }
+
+    fn visit_return(&mut self, return_stmt: &Return) {
+        if self.current_function == FunctionType::None {
+            self.error(&return_stmt.keyword, "Can't return from top-level code.");
+        }
+        if let Some(value) = return_stmt.value {
+            self.expr(value);
+        }
+    }
+
+    fn visit_var(&mut self, var: &Var) {
+        self.declare(&var.name);
+        if let Some(initializer) = var.initializer {
+            self.expr(initializer);
+        }
+        self.define(&var.name);
+    }
+
+    fn visit_while(&mut self, while_stmt: &While) {
+        self.expr(while_stmt.condition);
+        self.stmt(&while_stmt.body);
+        if let Some(increment) = while_stmt.increment {
+            self.expr(increment);
+        }
+    }
+}
+
+impl ExprVisitor<()> for Resolver<'_> {
+    fn visit_assign(&mut self, id: ExprId, assign: &Assign) {
+        self.expr(assign.value);
+        self.resolve_local(id, &assign.name);
+    }
+
+    fn visit_binary(&mut self, _: ExprId, binary: &Binary) {
+        self.expr(binary.left);
+        self.expr(binary.right);
+    }
+
+    fn visit_call(&mut self, _: ExprId, call: &Call) {
+        self.expr(call.callee);
+        for &argument in &call.arguments {
+            self.expr(argument);
+        }
+    }
+
+    fn visit_grouping(&mut self, _: ExprId, expr: ExprId) {
+        self.expr(expr);
+    }
+
+    fn visit_literal(&mut self, _: ExprId, _: &Literal) {}
+
+    fn visit_logical(&mut self, _: ExprId, logical: &Logical) {
+        self.expr(logical.left);
+        self.expr(logical.right);
+    }
+
+    fn visit_unary(&mut self, _: ExprId, unary: &Unary) {
+        self.expr(unary.right);
+    }
+
+    fn visit_variable(&mut self, id: ExprId, name: &Token) {
+        if self.scopes.last().and_then(|scope| scope.get(&name.lexeme)) == Some(&false) {
+            self.error(name, "Can't read local variable in its own initializer.");
+        }
+        self.resolve_local(id, name);
+    }
+}
@@ -3,24 +3,42 @@ use std::collections::HashMap;
 use crate::{
     error,
     expr::Literal,
-    token::{Token, TokenType},
+    token::{Span, Token, TokenType},
 };
 
+/// A lexical error. The scanner skips the offending text and keeps going.
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    pub message: &'static str,
+    pub line_num: usize,
+    pub span: Span,
+}
+
+impl ScanError {
+    pub fn report(&self) {
+        error(self.line_num, self.message);
+    }
+}
+
 #[derive(Default)]
 pub struct Scanner {
     source: Vec<char>,
-    tokens: Vec<Token>,
     start_index: usize,
     current_index: usize,
     line_num: usize,
     keywords: HashMap<&'static str, TokenType>,
+    is_finished: bool,
+    keep_comments: bool,
+    errors: Vec<ScanError>,
 }
 
 impl Scanner {
     pub fn new(source: Vec<char>) -> Self {
         let keywords = HashMap::from([
             ("and", TokenType::And),
+            ("break", TokenType::Break),
             ("class", TokenType::Class),
+            ("continue", TokenType::Continue),
             ("else", TokenType::Else),
             ("false", TokenType::False),
             ("for", TokenType::For),
@@ -40,22 +58,21 @@ impl Scanner {
         Scanner {
             source,
             keywords,
+            line_num: 1,
             ..Default::default()
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Vec<Token> {
-        while !self.is_at_end() {
-            // We are at the beginning of the next lexeme.
-            self.start_index = self.current_index;
-            self.scan_token();
-        }
-        self.tokens.push(Token {
-            token_type: TokenType::Eof,
-            line_num: self.line_num,
-            ..Default::default()
-        });
-        self.tokens.clone()
+    /// Lexical errors found so far, in source order.
+    pub fn errors(&self) -> &[ScanError] {
+        &self.errors
+    }
+
+    /// Emits comments as [`TokenType::Comment`] tokens instead of skipping
+    /// them, for tools that need to reproduce the source.
+    pub fn with_comments(mut self) -> Self {
+        self.keep_comments = true;
+        self
     }
 
     fn is_at_end(&self) -> bool {
@@ -68,56 +85,60 @@ impl Scanner {
         c
     }
 
-    fn add_token(&mut self, token_type: TokenType, literal: Literal) {
+    fn make_token(&self, token_type: TokenType, literal: Literal) -> Token {
         let lexeme = self.source[self.start_index..self.current_index]
             .iter()
             .collect();
-        self.tokens.push(Token {
+        Token {
             token_type,
             lexeme,
             line_num: self.line_num,
             literal,
-        })
+            span: Span {
+                start: self.start_index,
+                end: self.current_index,
+            },
+        }
     }
 
-    fn scan_token(&mut self) {
+    fn scan_token(&mut self) -> Option<Token> {
         match self.advance() {
-            '(' => self.add_token(TokenType::LeftParen, Literal::Nil),
-            ')' => self.add_token(TokenType::RightParen, Literal::Nil),
-            '{' => self.add_token(TokenType::LeftBrace, Literal::Nil),
-            '}' => self.add_token(TokenType::RightBrace, Literal::Nil),
-            ',' => self.add_token(TokenType::Comma, Literal::Nil),
-            '.' => self.add_token(TokenType::Dot, Literal::Nil),
-            '-' => self.add_token(TokenType::Minus, Literal::Nil),
-            '+' => self.add_token(TokenType::Plus, Literal::Nil),
-            ';' => self.add_token(TokenType::Semicolon, Literal::Nil),
-            '*' => self.add_token(TokenType::Star, Literal::Nil),
+            '(' => Some(self.make_token(TokenType::LeftParen, Literal::Nil)),
+            ')' => Some(self.make_token(TokenType::RightParen, Literal::Nil)),
+            '{' => Some(self.make_token(TokenType::LeftBrace, Literal::Nil)),
+            '}' => Some(self.make_token(TokenType::RightBrace, Literal::Nil)),
+            ',' => Some(self.make_token(TokenType::Comma, Literal::Nil)),
+            '.' => Some(self.make_token(TokenType::Dot, Literal::Nil)),
+            '-' => Some(self.make_token(TokenType::Minus, Literal::Nil)),
+            '+' => Some(self.make_token(TokenType::Plus, Literal::Nil)),
+            ';' => Some(self.make_token(TokenType::Semicolon, Literal::Nil)),
+            '*' => Some(self.make_token(TokenType::Star, Literal::Nil)),
             '!' => {
                 if self.match_char('=') {
-                    self.add_token(TokenType::BangEqual, Literal::Nil);
+                    Some(self.make_token(TokenType::BangEqual, Literal::Nil))
                 } else {
-                    self.add_token(TokenType::Bang, Literal::Nil);
+                    Some(self.make_token(TokenType::Bang, Literal::Nil))
                 }
             }
             '=' => {
                 if self.match_char('=') {
-                    self.add_token(TokenType::EqualEqual, Literal::Nil);
+                    Some(self.make_token(TokenType::EqualEqual, Literal::Nil))
                 } else {
-                    self.add_token(TokenType::Equal, Literal::Nil);
+                    Some(self.make_token(TokenType::Equal, Literal::Nil))
                 }
             }
             '<' => {
                 if self.match_char('=') {
-                    self.add_token(TokenType::LessEqual, Literal::Nil);
+                    Some(self.make_token(TokenType::LessEqual, Literal::Nil))
                 } else {
-                    self.add_token(TokenType::Less, Literal::Nil);
+                    Some(self.make_token(TokenType::Less, Literal::Nil))
                 }
             }
             '>' => {
                 if self.match_char('=') {
-                    self.add_token(TokenType::GreaterEqual, Literal::Nil);
+                    Some(self.make_token(TokenType::GreaterEqual, Literal::Nil))
                 } else {
-                    self.add_token(TokenType::Greater, Literal::Nil);
+                    Some(self.make_token(TokenType::Greater, Literal::Nil))
                 }
             }
             '/' => {
@@ -126,21 +147,40 @@ impl Scanner {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    self.comment()
                 } else if self.match_char('*') {
                     self.block_comment();
+                    self.comment()
                 } else {
-                    self.add_token(TokenType::Slash, Literal::Nil);
+                    Some(self.make_token(TokenType::Slash, Literal::Nil))
                 }
             }
-            ' ' | '\r' | '\t' => (),
-            '\n' => self.line_num += 1,
+            ' ' | '\r' | '\t' => None,
+            '\n' => {
+                self.line_num += 1;
+                None
+            }
             '"' => self.string(),
             c if c.is_ascii_digit() => self.number(),
-            c if c.is_ascii_alphabetic() || c == '_' => self.identifier(),
-            _ => error(self.line_num, "Unexpected character."),
+            c if c.is_ascii_alphabetic() || c == '_' => Some(self.identifier()),
+            _ => {
+                self.error("Unexpected character.");
+                None
+            }
         }
     }
 
+    fn error(&mut self, message: &'static str) {
+        self.errors.push(ScanError {
+            message,
+            line_num: self.line_num,
+            span: Span {
+                start: self.start_index,
+                end: self.current_index,
+            },
+        });
+    }
+
     fn match_char(&mut self, expected: char) -> bool {
         if self.is_at_end() {
             return false;
@@ -160,7 +200,7 @@ impl Scanner {
         }
     }
 
-    fn string(&mut self) {
+    fn string(&mut self) -> Option<Token> {
         while self.peek() != '"' && !self.is_at_end() {
             if self.peek() == '\n' {
                 self.line_num += 1;
@@ -169,25 +209,27 @@ impl Scanner {
         }
 
         if self.is_at_end() {
-            error(self.line_num, "Unterminated string.");
-            return;
+            self.error("Unterminated string.");
+            return None;
         }
 
         // the closing ".
         self.advance();
 
         // Trim the surrounding quotes.
-        self.add_token(
-            TokenType::String,
-            Literal::String(
-                self.source[self.start_index + 1..self.current_index - 1]
-                    .iter()
-                    .collect::<String>(),
+        Some(
+            self.make_token(
+                TokenType::String,
+                Literal::String(
+                    self.source[self.start_index + 1..self.current_index - 1]
+                        .iter()
+                        .collect::<String>(),
+                ),
             ),
-        );
+        )
     }
 
-    fn number(&mut self) {
+    fn number(&mut self) -> Option<Token> {
         while self.peek().is_ascii_digit() {
             self.advance();
         }
@@ -202,16 +244,16 @@ impl Scanner {
             }
         }
 
-        self.add_token(
-            TokenType::Number,
-            Literal::Number(
-                self.source[self.start_index..self.current_index]
-                    .iter()
-                    .collect::<String>()
-                    .parse()
-                    .unwrap(),
-            ),
-        );
+        let text: String = self.source[self.start_index..self.current_index]
+            .iter()
+            .collect();
+        match text.parse() {
+            Ok(number) => Some(self.make_token(TokenType::Number, Literal::Number(number))),
+            Err(_) => {
+                self.error("Invalid number.");
+                None
+            }
+        }
     }
 
     fn peek_next(&mut self) -> char {
@@ -221,7 +263,7 @@ impl Scanner {
         self.source[self.current_index + 1]
     }
 
-    fn identifier(&mut self) {
+    fn identifier(&mut self) -> Token {
         while self.peek().is_ascii_alphabetic()
             || self.peek() == '_'
             || self.peek().is_ascii_digit()
@@ -236,27 +278,65 @@ impl Scanner {
         if self.keywords.contains_key(text.as_str()) {
             token_type = self.keywords[text.as_str()];
         }
-        self.add_token(token_type, Literal::Nil);
+        self.make_token(token_type, Literal::Nil)
+    }
+
+    fn comment(&self) -> Option<Token> {
+        self.keep_comments
+            .then(|| self.make_token(TokenType::Comment, Literal::Nil))
     }
 
     fn block_comment(&mut self) {
         while !self.is_at_end() {
             match self.advance() {
-                '*' => {
-                    // end
-                    if self.match_char('/') {
-                        break;
-                    }
-                }
-                '/' => {
-                    // start
-                    if self.match_char('*') {
-                        self.block_comment();
-                    }
-                }
+                // end
+                '*' if self.match_char('/') => break,
+                // start
+                '/' if self.match_char('*') => self.block_comment(),
                 '\n' => self.line_num += 1,
                 _ => (),
             }
         }
     }
 }
+
+impl Iterator for Scanner {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        while !self.is_at_end() {
+            // We are at the beginning of the next lexeme.
+            self.start_index = self.current_index;
+            if let Some(token) = self.scan_token() {
+                return Some(token);
+            }
+        }
+
+        if self.is_finished {
+            return None;
+        }
+        self.is_finished = true;
+        Some(Token {
+            token_type: TokenType::Eof,
+            line_num: self.line_num,
+            span: Span {
+                start: self.current_index,
+                end: self.current_index,
+            },
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_numbered_from_one() {
+        let mut scanner = Scanner::new("print @;\nprint 2;".chars().collect());
+        let lines: Vec<usize> = scanner.by_ref().map(|token| token.line_num).collect();
+        assert_eq!(lines, [1, 1, 2, 2, 2, 2]);
+        assert_eq!(scanner.errors()[0].line_num, 1);
+    }
+}
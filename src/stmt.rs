@@ -0,0 +1,96 @@
+use std::rc::Rc;
+
+use crate::expr::ExprId;
+use crate::token::{Span, Token};
+
+/// A statement, with the span of source it was parsed from.
+#[derive(Debug)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug)]
+pub enum StmtKind {
+    Block(Vec<Stmt>),
+    Break(Token),
+    Continue(Token),
+    Expression(ExprId),
+    // Shared with the functions created from it at runtime.
+    Function(Rc<Function>),
+    If(If),
+    Print(ExprId),
+    Return(Return),
+    Var(Var),
+    While(While),
+}
+
+impl Stmt {
+    /// Calls the `visitor` method for this statement's variant.
+    pub fn accept<R, V>(&self, visitor: &mut V) -> R
+    where
+        V: StmtVisitor<R> + ?Sized,
+    {
+        match &self.kind {
+            StmtKind::Block(statements) => visitor.visit_block(statements),
+            StmtKind::Break(keyword) => visitor.visit_break(keyword),
+            StmtKind::Continue(keyword) => visitor.visit_continue(keyword),
+            StmtKind::Expression(expr) => visitor.visit_expression(*expr),
+            StmtKind::Function(function) => visitor.visit_function(function),
+            StmtKind::If(if_stmt) => visitor.visit_if(if_stmt),
+            StmtKind::Print(expr) => visitor.visit_print(*expr),
+            StmtKind::Return(return_stmt) => visitor.visit_return(return_stmt),
+            StmtKind::Var(var) => visitor.visit_var(var),
+            StmtKind::While(while_stmt) => visitor.visit_while(while_stmt),
+        }
+    }
+}
+
+/// A pass over statements, with one method per [`Stmt`] variant.
+pub trait StmtVisitor<R> {
+    fn visit_block(&mut self, statements: &[Stmt]) -> R;
+    fn visit_break(&mut self, keyword: &Token) -> R;
+    fn visit_continue(&mut self, keyword: &Token) -> R;
+    fn visit_expression(&mut self, expr: ExprId) -> R;
+    fn visit_function(&mut self, function: &Rc<Function>) -> R;
+    fn visit_if(&mut self, if_stmt: &If) -> R;
+    fn visit_print(&mut self, expr: ExprId) -> R;
+    fn visit_return(&mut self, return_stmt: &Return) -> R;
+    fn visit_var(&mut self, var: &Var) -> R;
+    fn visit_while(&mut self, while_stmt: &While) -> R;
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug)]
+pub struct If {
+    pub condition: ExprId,
+    pub then_branch: Box<Stmt>,
+    pub else_branch: Option<Box<Stmt>>,
+}
+
+#[derive(Debug)]
+pub struct Return {
+    pub keyword: Token,
+    pub value: Option<ExprId>,
+}
+
+#[derive(Debug)]
+pub struct Var {
+    pub name: Token,
+    pub initializer: Option<ExprId>,
+}
+
+#[derive(Debug)]
+pub struct While {
+    pub condition: ExprId,
+    pub body: Box<Stmt>,
+    // The increment clause of a `for` loop, which runs after the body
+    // even when it's cut short by `continue`.
+    pub increment: Option<ExprId>,
+}
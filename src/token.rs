@@ -35,7 +35,9 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Break,
     Class,
+    Continue,
     Else,
     False,
     Fun,
@@ -51,15 +53,36 @@ pub enum TokenType {
     Var,
     While,
 
+    // Only produced by a scanner built with `Scanner::with_comments`.
+    Comment,
+
     Eof,
 }
 
+/// Half-open range of character offsets into the source.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Literal,
     pub line_num: usize,
+    pub span: Span,
 }
 
 impl fmt::Display for Token {
@@ -0,0 +1,75 @@
+//! Runs every `examples/*.lox` script and compares what it prints with the
+//! sibling `.expected` file. A script starting with a `// args: ...` line
+//! is run with those arguments before its path. Rewrite the expected files
+//! after an intended output change with
+//! `cargo test --test examples -- --update`.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+fn main() {
+    let update = env::args().any(|arg| arg == "--update");
+    let mut failures = 0;
+    let scripts = scripts();
+    for script in &scripts {
+        let actual = run(script);
+        let expected_path = script.with_extension("expected");
+        if update {
+            fs::write(&expected_path, &actual).unwrap();
+            println!("updated {}", expected_path.display());
+            continue;
+        }
+
+        let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+        if actual == expected {
+            println!("ok {}", script.display());
+        } else {
+            failures += 1;
+            println!("FAILED {}", script.display());
+            println!("--- expected\n{expected}--- actual\n{actual}");
+        }
+    }
+
+    println!("{} examples, {failures} failed", scripts.len());
+    if failures > 0 {
+        process::exit(1);
+    }
+}
+
+fn scripts() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples");
+    let mut scripts: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lox"))
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+// Stdout, then stderr and the exit status when there is anything to show.
+fn run(script: &Path) -> String {
+    let source = fs::read_to_string(script).unwrap();
+    let args = source
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("// args:"))
+        .unwrap_or_default();
+    let output = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .args(args.split_whitespace())
+        .arg(script)
+        .output()
+        .unwrap();
+    let mut actual = String::from_utf8_lossy(&output.stdout).into_owned();
+    if !output.stderr.is_empty() {
+        actual.push_str("--- stderr\n");
+        actual.push_str(&String::from_utf8_lossy(&output.stderr));
+    }
+    if !output.status.success() {
+        actual.push_str(&format!("--- {}\n", output.status));
+    }
+    actual
+}
@@ -0,0 +1,65 @@
+//! Checks the parser's nesting limit on sources too large to keep as
+//! examples. Hitting it is reported once, however deep the input goes.
+
+use std::{fs, path::PathBuf, process::Command};
+
+// How deeply blocks and expressions may nest, as in the parser.
+const MAX_DEPTH: usize = 1000;
+
+// Runs `source` as a script, returning its exit code and stderr.
+fn run(name: &str, source: &str) -> (Option<i32>, String) {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::write(&path, source).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+fn blocks(depth: usize) -> String {
+    "{".repeat(depth) + &"}".repeat(depth)
+}
+
+// A chain of `terms` additions builds a tree one level taller per `+`.
+fn chain(terms: usize) -> String {
+    format!("print {};", vec!["1"; terms].join(" + "))
+}
+
+#[test]
+fn blocks_nest_up_to_the_limit() {
+    assert_eq!(
+        run("blocks_at_limit.lox", &blocks(MAX_DEPTH)),
+        (Some(0), String::new())
+    );
+}
+
+#[test]
+fn blocks_past_the_limit_are_reported_once() {
+    let (status, stderr) = run("blocks_past_limit.lox", &blocks(100 * MAX_DEPTH));
+    assert_eq!(status, Some(65));
+    assert_eq!(stderr, "[line 1] Error at '{': Block too deeply nested.\n");
+}
+
+#[test]
+fn functions_past_the_limit_are_reported_once() {
+    let source = "fun f() {\n".repeat(100 * MAX_DEPTH);
+    let (status, stderr) = run("functions_past_limit.lox", &source);
+    assert_eq!(status, Some(65));
+    assert_eq!(stderr.lines().count(), 1, "{stderr}");
+    assert!(stderr.ends_with("Block too deeply nested.\n"), "{stderr}");
+}
+
+#[test]
+fn chains_count_toward_the_limit() {
+    assert_eq!(run("chain_at_limit.lox", &chain(MAX_DEPTH)).0, Some(0));
+    let (status, stderr) = run("chain_past_limit.lox", &chain(100 * MAX_DEPTH));
+    assert_eq!(status, Some(65));
+    assert_eq!(
+        stderr,
+        "[line 1] Error at '1': Expression too deeply nested.\n"
+    );
+}
@@ -0,0 +1,28 @@
+//! Times parsing a large generated script, to compare changes to how the
+//! syntax tree is built. Run it on a release build:
+//!
+//! ```text
+//! $ cargo test --release --test parse_time -- --ignored --nocapture
+//! ```
+
+use std::{fs, path::PathBuf, process::Command};
+
+// Each statement is as long a chain as the parser's nesting limit allows.
+const STATEMENTS: usize = 300;
+const TERMS: usize = 999;
+
+#[test]
+#[ignore = "benchmark"]
+fn parse_large_script() {
+    let statement = format!("{};\n", vec!["1"; TERMS].join(" + "));
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("parse_time.lox");
+    fs::write(&path, statement.repeat(STATEMENTS)).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_lox"))
+        .arg("--time")
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    println!("{} terms", STATEMENTS * TERMS);
+    print!("{}", String::from_utf8_lossy(&output.stderr));
+}